@@ -0,0 +1,11 @@
+-- 流量周期阈值触发记录，用于重启后恢复通知状态
+create table monitor_notify
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    cycle_start_date TIMESTAMP NOT NULL, -- 周期开始日期
+    cycle_end_date TIMESTAMP NOT NULL, -- 周期结束日期
+    percent int NOT NULL -- 触发的阈值百分比
+);
+
+create index idx_monitor_notify_cycle on monitor_notify (cycle_start_date, cycle_end_date);
@@ -0,0 +1,42 @@
+-- 监控数据增加网卡名称，支持同时监控多个网卡
+-- 历史数据的网卡名称为空，启动时会归属到配置中的第一个网卡
+alter table monitor_second add column network_name TEXT DEFAULT '' NOT NULL; -- 网卡名称
+
+create index idx_monitor_second_network_name on monitor_second (network_name, end_time);
+
+create table monitor_hour_new
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    network_name TEXT DEFAULT '' NOT NULL, -- 网卡名称
+    day TIMESTAMP NOT NULL, -- 日期
+    hour int NOT NULL, -- 小时，24小时制
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    UNIQUE (network_name, day, hour)
+);
+
+insert into monitor_hour_new (id, create_time, day, hour, uplink_traffic_usage, downlink_traffic_usage)
+select id, create_time, day, hour, uplink_traffic_usage, downlink_traffic_usage from monitor_hour;
+
+drop table monitor_hour;
+
+alter table monitor_hour_new rename to monitor_hour;
+
+create table monitor_day_new
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    network_name TEXT DEFAULT '' NOT NULL, -- 网卡名称
+    day TIMESTAMP NOT NULL, -- 日期
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    UNIQUE (network_name, day)
+);
+
+insert into monitor_day_new (id, create_time, day, uplink_traffic_usage, downlink_traffic_usage)
+select id, create_time, day, uplink_traffic_usage, downlink_traffic_usage from monitor_day;
+
+drop table monitor_day;
+
+alter table monitor_day_new rename to monitor_day;
@@ -0,0 +1,20 @@
+-- 秒级监控数据记录开机 id 和网卡序号，用于区分重启、网卡重建和计数器回绕
+alter table monitor_second add column boot_id TEXT; -- 开机 id，/proc/sys/kernel/random/boot_id
+alter table monitor_second add column ifindex int; -- 网卡序号，/sys/class/net/<网卡>/ifindex
+
+create table monitor_reset
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    network_name TEXT NOT NULL, -- 网卡名称
+    reset_time TIMESTAMP NOT NULL, -- 发现重置的采集时间
+    reason TEXT NOT NULL, -- 重置原因
+    pre_boot_id TEXT, -- 上一次开机 id
+    boot_id TEXT, -- 本次开机 id
+    pre_ifindex int, -- 上一次网卡序号
+    ifindex int, -- 本次网卡序号
+    pre_uplink_traffic_readings int NOT NULL, -- 上一次上行流量读数
+    uplink_traffic_readings int NOT NULL, -- 本次上行流量读数
+    pre_downlink_traffic_readings int NOT NULL, -- 上一次下行流量读数
+    downlink_traffic_readings int NOT NULL -- 本次下行流量读数
+);
@@ -0,0 +1,4 @@
+-- 应用停止期间的流量无法得知具体分布，按时长分摊到各小时后标记为估算数据
+alter table monitor_second add column is_estimated int DEFAULT 0 NOT NULL; -- 是否是估算数据
+alter table monitor_hour add column is_estimated int DEFAULT 0 NOT NULL; -- 是否包含估算数据
+alter table monitor_day add column is_estimated int DEFAULT 0 NOT NULL; -- 是否包含估算数据
@@ -0,0 +1,27 @@
+-- 记录包数、错误数、丢包数，用于排查包量攻击和网卡问题
+alter table monitor_second add column uplink_packets_readings int; -- 上行包数读数
+alter table monitor_second add column downlink_packets_readings int; -- 下行包数读数
+alter table monitor_second add column uplink_packets_usage int; -- 上行包数用量
+alter table monitor_second add column downlink_packets_usage int; -- 下行包数用量
+alter table monitor_second add column uplink_errors_readings int; -- 上行错误数读数
+alter table monitor_second add column downlink_errors_readings int; -- 下行错误数读数
+alter table monitor_second add column uplink_errors_usage int; -- 上行错误数用量
+alter table monitor_second add column downlink_errors_usage int; -- 下行错误数用量
+alter table monitor_second add column uplink_drops_readings int; -- 上行丢包数读数
+alter table monitor_second add column downlink_drops_readings int; -- 下行丢包数读数
+alter table monitor_second add column uplink_drops_usage int; -- 上行丢包数用量
+alter table monitor_second add column downlink_drops_usage int; -- 下行丢包数用量
+
+alter table monitor_hour add column uplink_packets_usage int DEFAULT 0 NOT NULL; -- 上行包数用量
+alter table monitor_hour add column downlink_packets_usage int DEFAULT 0 NOT NULL; -- 下行包数用量
+alter table monitor_hour add column uplink_errors_usage int DEFAULT 0 NOT NULL; -- 上行错误数用量
+alter table monitor_hour add column downlink_errors_usage int DEFAULT 0 NOT NULL; -- 下行错误数用量
+alter table monitor_hour add column uplink_drops_usage int DEFAULT 0 NOT NULL; -- 上行丢包数用量
+alter table monitor_hour add column downlink_drops_usage int DEFAULT 0 NOT NULL; -- 下行丢包数用量
+
+alter table monitor_day add column uplink_packets_usage int DEFAULT 0 NOT NULL; -- 上行包数用量
+alter table monitor_day add column downlink_packets_usage int DEFAULT 0 NOT NULL; -- 下行包数用量
+alter table monitor_day add column uplink_errors_usage int DEFAULT 0 NOT NULL; -- 上行错误数用量
+alter table monitor_day add column downlink_errors_usage int DEFAULT 0 NOT NULL; -- 下行错误数用量
+alter table monitor_day add column uplink_drops_usage int DEFAULT 0 NOT NULL; -- 上行丢包数用量
+alter table monitor_day add column downlink_drops_usage int DEFAULT 0 NOT NULL; -- 下行丢包数用量
@@ -0,0 +1,38 @@
+-- 采集时间改为 UTC 存储，按配置的时区统计小时和天
+-- 小时数据以本地整点开始时刻的 UTC 时间为唯一键，夏令时回拨时重复的本地小时不再冲突
+-- 历史数据为本地时间，启动时会按配置的时区转换为 UTC，并补全小时数据的开始时间
+create table monitor_hour_new
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    network_name TEXT DEFAULT '' NOT NULL, -- 网卡名称
+    start_time TIMESTAMP, -- 小时开始时间，UTC
+    day TIMESTAMP NOT NULL, -- 日期，配置时区
+    hour int NOT NULL, -- 小时，24小时制，配置时区
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    is_estimated int DEFAULT 0 NOT NULL, -- 是否包含估算数据
+    uplink_packets_usage int DEFAULT 0 NOT NULL, -- 上行包数用量
+    downlink_packets_usage int DEFAULT 0 NOT NULL, -- 下行包数用量
+    uplink_errors_usage int DEFAULT 0 NOT NULL, -- 上行错误数用量
+    downlink_errors_usage int DEFAULT 0 NOT NULL, -- 下行错误数用量
+    uplink_drops_usage int DEFAULT 0 NOT NULL, -- 上行丢包数用量
+    downlink_drops_usage int DEFAULT 0 NOT NULL, -- 下行丢包数用量
+    UNIQUE (network_name, start_time)
+);
+
+insert into monitor_hour_new (id, create_time, network_name, day, hour, uplink_traffic_usage, downlink_traffic_usage, is_estimated, uplink_packets_usage, downlink_packets_usage, uplink_errors_usage, downlink_errors_usage, uplink_drops_usage, downlink_drops_usage)
+select id, create_time, network_name, day, hour, uplink_traffic_usage, downlink_traffic_usage, is_estimated, uplink_packets_usage, downlink_packets_usage, uplink_errors_usage, downlink_errors_usage, uplink_drops_usage, downlink_drops_usage from monitor_hour;
+
+drop table monitor_hour;
+
+alter table monitor_hour_new rename to monitor_hour;
+
+create index idx_monitor_hour_day on monitor_hour (network_name, day, hour);
+
+-- 记录已执行过的数据转换，避免重复执行
+create table data_migration
+(
+    name        TEXT PRIMARY KEY NOT NULL, -- 转换名称
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL
+);
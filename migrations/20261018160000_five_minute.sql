@@ -0,0 +1,18 @@
+-- 5 分钟汇总数据，比小时数据精细，保留时间比秒级数据长，用于排查最近几周的问题
+create table monitor_five_minute
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    network_name TEXT NOT NULL, -- 网卡名称
+    start_time TIMESTAMP NOT NULL, -- 开始时间，UTC，5 分钟对齐
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    is_estimated int DEFAULT 0 NOT NULL, -- 是否包含估算数据
+    uplink_packets_usage int DEFAULT 0 NOT NULL, -- 上行包数用量
+    downlink_packets_usage int DEFAULT 0 NOT NULL, -- 下行包数用量
+    uplink_errors_usage int DEFAULT 0 NOT NULL, -- 上行错误数用量
+    downlink_errors_usage int DEFAULT 0 NOT NULL, -- 下行错误数用量
+    uplink_drops_usage int DEFAULT 0 NOT NULL, -- 上行丢包数用量
+    downlink_drops_usage int DEFAULT 0 NOT NULL, -- 下行丢包数用量
+    UNIQUE (network_name, start_time)
+);
@@ -0,0 +1,17 @@
+-- 流量周期历史，周期结束后记录整个周期的用量和触发的阈值
+create table monitor_cycle
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    cycle_start_date TIMESTAMP NOT NULL, -- 周期开始日期
+    cycle_end_date TIMESTAMP NOT NULL, -- 周期结束日期
+    network_name TEXT NOT NULL, -- 计入周期的网卡名称，多个用逗号分隔
+    traffic_limit int NOT NULL, -- 流量限制
+    statistic_method TEXT NOT NULL, -- 统计方式
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    traffic_usage int NOT NULL, -- 计入流量
+    notify_percent TEXT DEFAULT '' NOT NULL, -- 触发的阈值百分比，多个用逗号分隔
+    exceeded int DEFAULT 0 NOT NULL, -- 是否超出流量限制
+    UNIQUE (cycle_start_date, cycle_end_date)
+);
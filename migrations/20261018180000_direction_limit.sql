@@ -0,0 +1,28 @@
+-- 流量周期支持分别限制上行和下行流量，阈值可以指定统计方向
+alter table monitor_notify add column target TEXT DEFAULT 'total' NOT NULL; -- 阈值统计方向 total uplink downlink
+
+create table monitor_cycle_new
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    cycle_start_date TIMESTAMP NOT NULL, -- 周期开始日期
+    cycle_end_date TIMESTAMP NOT NULL, -- 周期结束日期
+    network_name TEXT NOT NULL, -- 计入周期的网卡名称，多个用逗号分隔
+    traffic_limit int, -- 流量限制，没有配置时为空
+    uplink_limit int, -- 上行流量限制，没有配置时为空
+    downlink_limit int, -- 下行流量限制，没有配置时为空
+    statistic_method TEXT NOT NULL, -- 统计方式
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    traffic_usage int NOT NULL, -- 计入流量
+    notify_percent TEXT DEFAULT '' NOT NULL, -- 触发的阈值百分比，多个用逗号分隔，非 total 方向的阈值带方向前缀，例如 uplink:80
+    exceeded int DEFAULT 0 NOT NULL, -- 是否超出任意一项流量限制
+    UNIQUE (cycle_start_date, cycle_end_date)
+);
+
+insert into monitor_cycle_new (id, create_time, cycle_start_date, cycle_end_date, network_name, traffic_limit, statistic_method, uplink_traffic_usage, downlink_traffic_usage, traffic_usage, notify_percent, exceeded)
+select id, create_time, cycle_start_date, cycle_end_date, network_name, traffic_limit, statistic_method, uplink_traffic_usage, downlink_traffic_usage, traffic_usage, notify_percent, exceeded from monitor_cycle;
+
+drop table monitor_cycle;
+
+alter table monitor_cycle_new rename to monitor_cycle;
@@ -0,0 +1,33 @@
+-- 支持同时配置多个流量周期(额度)，阈值触发记录和周期历史按额度名称区分，历史数据归属到 default
+alter table monitor_notify add column quota_name TEXT DEFAULT 'default' NOT NULL; -- 流量周期名称
+
+drop index idx_monitor_notify_cycle;
+
+create index idx_monitor_notify_cycle on monitor_notify (quota_name, cycle_start_date, cycle_end_date);
+
+create table monitor_cycle_new
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    quota_name TEXT DEFAULT 'default' NOT NULL, -- 流量周期名称
+    cycle_start_date TIMESTAMP NOT NULL, -- 周期开始日期
+    cycle_end_date TIMESTAMP NOT NULL, -- 周期结束日期
+    network_name TEXT NOT NULL, -- 计入周期的网卡名称，多个用逗号分隔
+    traffic_limit int, -- 流量限制，没有配置时为空
+    uplink_limit int, -- 上行流量限制，没有配置时为空
+    downlink_limit int, -- 下行流量限制，没有配置时为空
+    statistic_method TEXT NOT NULL, -- 统计方式
+    uplink_traffic_usage int NOT NULL, -- 上行流量用量
+    downlink_traffic_usage int NOT NULL, -- 下行流量用量
+    traffic_usage int NOT NULL, -- 计入流量
+    notify_percent TEXT DEFAULT '' NOT NULL, -- 触发的阈值百分比，多个用逗号分隔，非 total 方向的阈值带方向前缀，例如 uplink:80
+    exceeded int DEFAULT 0 NOT NULL, -- 是否超出任意一项流量限制
+    UNIQUE (quota_name, cycle_start_date, cycle_end_date)
+);
+
+insert into monitor_cycle_new (id, create_time, cycle_start_date, cycle_end_date, network_name, traffic_limit, uplink_limit, downlink_limit, statistic_method, uplink_traffic_usage, downlink_traffic_usage, traffic_usage, notify_percent, exceeded)
+select id, create_time, cycle_start_date, cycle_end_date, network_name, traffic_limit, uplink_limit, downlink_limit, statistic_method, uplink_traffic_usage, downlink_traffic_usage, traffic_usage, notify_percent, exceeded from monitor_cycle;
+
+drop table monitor_cycle;
+
+alter table monitor_cycle_new rename to monitor_cycle;
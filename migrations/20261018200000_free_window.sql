@@ -0,0 +1,3 @@
+-- 流量周期支持免费时段，周期历史单独记录免费时段内的流量
+alter table monitor_cycle add column free_uplink_traffic_usage int DEFAULT 0 NOT NULL; -- 免费时段上行流量
+alter table monitor_cycle add column free_downlink_traffic_usage int DEFAULT 0 NOT NULL; -- 免费时段下行流量
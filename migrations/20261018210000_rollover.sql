@@ -0,0 +1,4 @@
+-- 流量周期支持结转上一周期未用完的流量，周期历史中的限制为加上结转后的实际限制
+alter table monitor_cycle add column traffic_rollover int; -- 从上一周期结转的流量，没有配置结转时为空
+alter table monitor_cycle add column uplink_rollover int; -- 从上一周期结转的上行流量
+alter table monitor_cycle add column downlink_rollover int; -- 从上一周期结转的下行流量
@@ -0,0 +1,4 @@
+-- 流量周期支持按 5 分钟采样的 95 计费带宽计费
+alter table monitor_cycle add column billing_mode TEXT DEFAULT 'volume' NOT NULL; -- 计费方式，按流量: volume  按 95 计费带宽: p95
+alter table monitor_cycle add column committed_rate int; -- 承诺带宽，单位 bit/s，按流量计费时为空
+alter table monitor_cycle add column rate_p95 int; -- 周期的 95 计费带宽，单位 bit/s，按流量计费时为空
@@ -0,0 +1,5 @@
+-- 流量周期支持超额计费，周期历史记录超额费用，阈值支持按超额费用触发
+alter table monitor_cycle add column overage_cost TEXT; -- 超额费用，没有配置超额计费时为空
+alter table monitor_cycle add column currency TEXT; -- 超额费用的货币
+
+alter table monitor_notify add column cost TEXT; -- 按超额费用触发的阈值金额，其他阈值为空
@@ -0,0 +1,2 @@
+-- 流量周期支持每日额度，记录超过每日额度和执行 end_exec 的周期日，重启后恢复
+alter table monitor_notify add column day TIMESTAMP; -- 每日额度触发(pacing)或恢复(pacing_end)的周期日，其他阈值为空
@@ -0,0 +1,2 @@
+-- 阈值支持小数百分比、已用量、剩余量和周期剩余天数，触发记录按统计方向和条件区分
+alter table monitor_notify add column condition TEXT; -- 阈值条件标识，例如 percent:99.5,remaining_days:5，为空时为只有 percent 或 cost 的历史记录
@@ -0,0 +1,19 @@
+-- 阈值、每日额度、周期切换时执行的动作记录
+create table monitor_event
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    create_time TIMESTAMP DEFAULT (datetime(CURRENT_TIMESTAMP, 'localtime')) NOT NULL,
+    quota_name TEXT NOT NULL, -- 流量周期名称
+    event TEXT NOT NULL, -- 触发原因 notify pacing pacing_end cycle_start cycle_end
+    target TEXT, -- 阈值统计方向，其他触发原因为空
+    condition TEXT, -- 阈值条件标识，其他触发原因为空
+    action_type TEXT NOT NULL, -- 动作类型 shell http signal systemd-unit
+    action TEXT NOT NULL, -- 执行的命令或请求地址
+    success int NOT NULL, -- 是否执行成功
+    attempts int NOT NULL, -- 执行次数，包含重试
+    output TEXT, -- 最后一次执行的输出或错误信息
+    start_time TIMESTAMP NOT NULL, -- 开始执行时间 UTC
+    duration_ms int NOT NULL -- 执行耗时毫秒，包含重试间隔
+);
+
+create index idx_monitor_event_start_time on monitor_event (start_time);
@@ -1,24 +1,249 @@
 use serde_inline_default::serde_inline_default;
 
-use serde::{Serialize, Deserialize};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::{Serialize, Deserialize, Deserializer};
 use crate::util::file_util;
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        }
+    }
+}
+
+/// 网卡名称既可以写成单个字符串 "eth0"，也可以写成数组 ["eth0", "wg0"]
+fn network_name_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(OneOrMany::<String>::deserialize(deserializer)?.into())
+}
+
+/// 可选的列表配置既可以写成单个值，也可以写成数组
+fn option_one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<Vec<T>>, D::Error> {
+    Ok(Option::<OneOrMany<T>>::deserialize(deserializer)?.map(Into::into))
+}
+
+#[serde_inline_default]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrafficCycleNotifyConfig {
+    #[serde(default)]
+    pub percent: Decimal,
+    pub used: Option<String>,
+    pub remaining: Option<String>,
+    pub remaining_days: Option<u32>,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub exec: Option<Vec<ActionConfig>>,
+    #[serde_inline_default("total".to_string())]
+    pub target: String,
+    pub cost: Option<Decimal>,
+}
+
+/// 阈值、每日额度、周期切换时执行的动作，字符串为 shell 命令，对象按 type 区分类型
+/// 配置中既可以写成单个动作，也可以写成数组依次执行
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ActionConfig {
+    Command(String),
+    Typed(TypedActionConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TypedActionConfig {
+    Shell(ShellActionConfig),
+    Http(HttpActionConfig),
+    Signal(SignalActionConfig),
+    SystemdUnit(SystemdUnitActionConfig),
+}
+
+/// shell 命令，通过 TM_ 开头的环境变量获取触发原因和用量
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShellActionConfig {
+    pub command: String,
+    #[serde_inline_default(60)]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub retries: u32,
+    pub env: Option<std::collections::HashMap<String, String>>,
+}
+
+/// webhook，url 和 body 中的 {{usage}} 这样的变量会被替换
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpActionConfig {
+    pub url: String,
+    #[serde_inline_default("POST".to_string())]
+    pub method: String,
+    pub headers: Option<std::collections::HashMap<String, String>>,
+    pub body: Option<String>,
+    #[serde_inline_default(30)]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// 向进程发送信号，pid pid_file process_name 填写其中一个
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SignalActionConfig {
+    #[serde_inline_default("TERM".to_string())]
+    pub signal: String,
+    pub pid: Option<u32>,
+    pub pid_file: Option<String>,
+    pub process_name: Option<String>,
+}
+
+/// 操作 systemd 服务，在 docker 中时 nsenter 填 true 通过 nsenter 在宿主机执行
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SystemdUnitActionConfig {
+    pub unit: String,
+    #[serde_inline_default("restart".to_string())]
+    pub operation: String,
+    #[serde(default)]
+    pub nsenter: bool,
+}
+
+/// 超额计费，超出部分按阶梯价格累计，up_to 为超出量的上限，最后一档不填 up_to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PricingTierConfig {
+    pub up_to: Option<String>,
+    pub price_per_gb: Decimal,
+}
+
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PricingConfig {
+    #[serde_inline_default("USD".to_string())]
+    pub currency: String,
+    pub included: Option<String>,
+    pub price_per_gb: Option<Decimal>,
+    pub tiers: Option<Vec<PricingTierConfig>>,
+}
+
+/// 免费时段，时段内的流量不计入流量周期的用量，按配置时区的整点小时判断
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FreeWindowConfig {
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub weekday: Option<Vec<String>>,
+    #[serde_inline_default("00:00".to_string())]
+    pub start: String,
+    #[serde_inline_default("24:00".to_string())]
+    pub end: String,
+}
+
+/// 未用完的流量结转到下一周期，结转 上一周期剩余 * percent%，最多结转 max
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RolloverConfig {
+    #[serde_inline_default(100)]
     pub percent: u8,
-    pub exec: Option<String>,
+    pub max: Option<String>,
 }
 
+/// 每日额度，剩余流量按剩余天数平均分配，当天计入流量超过每日额度的 multiple 倍时通知并执行 exec，进入下一天后执行 end_exec
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PacingConfig {
+    #[serde_inline_default(Decimal::ONE)]
+    pub multiple: Decimal,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub exec: Option<Vec<ActionConfig>>,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub end_exec: Option<Vec<ActionConfig>>,
+}
+
+/// 每月重置日，可以填写 1 ~ 31 的日期，超过当月天数时为当月最后一天，也可以填写 "last" 表示每月最后一天
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ResetDayConfig {
+    Day(u32),
+    Rule(String),
+}
+
+#[serde_inline_default]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrafficCycleConfig {
+    #[serde_inline_default("default".to_string())]
+    pub name: String,
     pub cycle_type: String,
     pub each: Option<i64>,
     pub traffic_reset_date: Option<String>,
+    pub reset_weekday: Option<String>,
+    pub reset_day: Option<ResetDayConfig>,
+    pub reset_hour: Option<u32>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
-    pub traffic_limit: String,
+    pub traffic_limit: Option<String>,
+    pub uplink_limit: Option<String>,
+    pub downlink_limit: Option<String>,
     pub statistic_method: String,
+    #[serde_inline_default("volume".to_string())]
+    pub billing_mode: String,
+    pub committed_rate: Option<String>,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub network_name: Option<Vec<String>>,
     pub notify: Option<Vec<TrafficCycleNotifyConfig>>,
+    pub free_windows: Option<Vec<FreeWindowConfig>>,
+    pub rollover: Option<RolloverConfig>,
+    pub pricing: Option<PricingConfig>,
+    pub pacing: Option<PacingConfig>,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub on_cycle_start: Option<Vec<ActionConfig>>,
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub on_cycle_end: Option<Vec<ActionConfig>>,
+}
+
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrafficSourceConfig {
+    #[serde_inline_default("systemstat".to_string())]
+    pub source_type: String,
+    pub path: Option<String>,
+}
+
+impl Default for TrafficSourceConfig {
+    fn default() -> Self {
+        TrafficSourceConfig {
+            source_type: "systemstat".to_string(),
+            path: None,
+        }
+    }
+}
+
+/// 各表数据保留天数(含今天)，0 为永久保存
+#[serde_inline_default]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetentionConfig {
+    #[serde_inline_default(2)]
+    pub second_days: i64,
+    #[serde_inline_default(35)]
+    pub five_minute_days: i64,
+    #[serde_inline_default(0)]
+    pub hour_days: i64,
+    #[serde_inline_default(0)]
+    pub day_days: i64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            second_days: 2,
+            five_minute_days: 35,
+            hour_days: 0,
+            day_days: 0,
+        }
+    }
 }
 
 #[serde_inline_default]
@@ -29,6 +254,8 @@ pub struct WebConfig {
     #[serde_inline_default("/ui".to_string())]
     pub ui_path: String,
     pub token: String,
+    #[serde_inline_default(600)]
+    pub live_buffer_secs: usize,
 }
 
 #[serde_inline_default]
@@ -45,13 +272,42 @@ pub struct TgConfig {
 #[serde_inline_default]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
-    pub network_name: String,
+    #[serde(deserialize_with = "network_name_list")]
+    pub network_name: Vec<String>,
     pub vps_name: String,
     #[serde_inline_default("info".to_string())]
     pub log_level: String,
+    /// IANA 时区名称，例如 Asia/Shanghai，用于按小时、天统计，流量周期和日志时间，数据库中的采集时间均为 UTC
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub traffic_source: TrafficSourceConfig,
+    #[serde_inline_default(15)]
+    pub sample_interval_secs: u64,
+    #[serde_inline_default("proportional".to_string())]
+    pub gap_strategy: String,
+    #[serde(default)]
+    pub retention: RetentionConfig,
     pub web: Option<WebConfig>,
     pub tg: Option<TgConfig>,
-    pub traffic_cycle: Option<TrafficCycleConfig>,
+    /// 流量周期既可以写成单个对象，也可以写成数组同时统计多个额度
+    #[serde(default, deserialize_with = "option_one_or_many")]
+    pub traffic_cycle: Option<Vec<TrafficCycleConfig>>,
+}
+
+/// 没有配置时区时依次使用 TZ 环境变量、系统时区，都获取不到时使用 UTC
+fn default_timezone() -> String {
+    std::env::var("TZ")
+        .ok()
+        .filter(|tz| tz.parse::<Tz>().is_ok())
+        .or_else(|| iana_time_zone::get_timezone().ok().filter(|tz| tz.parse::<Tz>().is_ok()))
+        .unwrap_or("UTC".to_string())
+}
+
+impl Config {
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap()
+    }
 }
 
 const CONFIG_FILE_NAME: &'static str = "config/config.json";
@@ -69,5 +325,32 @@ pub fn get_config() -> Config {
     let config: Config = serde_json::from_str(&buf).unwrap_or_else(|e| {
         panic!("配置文件 {} 可能不是 json 格式: {:?}", &filepath.display(), e);
     });
+    if config.network_name.is_empty() {
+        panic!("配置文件 {} 中 network_name 至少需要填写一个网卡", &filepath.display());
+    }
+    if config.sample_interval_secs == 0 {
+        panic!("配置文件 {} 中 sample_interval_secs 必须大于 0", &filepath.display());
+    }
+    let retention = &config.retention;
+    if [retention.second_days, retention.five_minute_days, retention.hour_days, retention.day_days].iter().any(|ele| *ele < 0) {
+        panic!("配置文件 {} 中 retention 的保留天数不能小于 0", &filepath.display());
+    }
+    // 天统计依赖前一天的小时数据
+    if retention.hour_days == 1 || retention.day_days == 1 {
+        panic!("配置文件 {} 中 retention 的 hour_days 和 day_days 至少保留 2 天", &filepath.display());
+    }
+    if let Some(traffic_cycle) = &config.traffic_cycle {
+        for (i, ele) in traffic_cycle.iter().enumerate() {
+            if traffic_cycle[..i].iter().any(|pre| pre.name == ele.name) {
+                panic!("配置文件 {} 中 traffic_cycle 的名称 {} 重复，多个流量周期需要填写不同的 name", &filepath.display(), ele.name);
+            }
+        }
+    }
+    if config.timezone.parse::<Tz>().is_err() {
+        panic!("配置文件 {} 中 timezone 不是有效的 IANA 时区名称: {}", &filepath.display(), config.timezone);
+    }
+    if !["proportional", "start"].contains(&config.gap_strategy.as_str()) {
+        panic!("配置文件 {} 中 gap_strategy 配置填写错误，只能填写 proportional 或 start: {}", &filepath.display(), config.gap_strategy);
+    }
     config
 }
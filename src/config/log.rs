@@ -1,13 +1,20 @@
 use std::str::FromStr;
 
-use time::UtcOffset;
-use tracing_subscriber::{filter::LevelFilter, Layer, prelude::*, fmt::time::OffsetTime};
+use chrono_tz::Tz;
+use tracing_subscriber::{filter::LevelFilter, Layer, prelude::*, fmt::{format::Writer, time::FormatTime}};
+
+/// 按配置时区输出日志时间
+#[derive(Clone)]
+struct TimezoneTime(Tz);
+
+impl FormatTime for TimezoneTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        write!(w, "{}", chrono::Utc::now().with_timezone(&self.0).format("%Y-%m-%d %H:%M:%S%.3f"))
+    }
+}
 
 pub fn init(config: &crate::config::app_config::Config) {
-    let local_time = OffsetTime::new(
-        UtcOffset::from_hms(8, 0, 0).unwrap(),
-        time::format_description::parse("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]").unwrap(),
-    );
+    let local_time = TimezoneTime(config.tz());
     let level = LevelFilter::from_str(&config.log_level).unwrap_or(LevelFilter::INFO);
     tracing_subscriber::registry()
         .with(
@@ -23,7 +23,12 @@ pub async fn init(app_state: AppState) -> Router {
         .route("/send_today_statistics", post(traffic_ctl::send_today_statistics))
         .route("/day", post(traffic_ctl::list_monitor_day))
         .route("/hour", post(traffic_ctl::list_monitor_hour))
-        .route("/second", post(traffic_ctl::list_monitor_second));
+        .route("/five_minute", post(traffic_ctl::list_monitor_five_minute))
+        .route("/second", post(traffic_ctl::list_monitor_second))
+        .route("/reset", post(traffic_ctl::list_monitor_reset))
+        .route("/event", post(traffic_ctl::list_monitor_event))
+        .route("/cycles", get(traffic_ctl::list_monitor_cycle))
+        .route("/live", get(traffic_ctl::live));
 
     let api = Router::new()
         .nest("/app", app)
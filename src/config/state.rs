@@ -1,53 +1,421 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
 
+use chrono::{Datelike, Timelike};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
 
+use crate::{
+    config::app_config::ActionConfig,
+    service::traffic_source_svc::TrafficSource,
+    util::{expr_util::{self, Expr}, time_util},
+};
+
+/// 计入流量的统计方法，由 statistic_method 表达式解析得到，序列化时保持原表达式
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum CycleStatisticMethod {
-    SumInOut,
-    MaxInOut,
-    OnlyOut,
+#[serde(try_from = "String", into = "String")]
+pub struct CycleStatisticMethod {
+    text: String,
+    expr: Expr,
+}
+
+impl CycleStatisticMethod {
+    /// 上下行用量按统计方法计算计入流量，所有计入流量的计算都经过这里，结果四舍五入到字节，小于 0 时为 0
+    pub fn traffic_usage(&self, uplink_traffic_usage: i64, downlink_traffic_usage: i64) -> i64 {
+        let res = expr_util::eval(&self.expr, Decimal::from(downlink_traffic_usage), Decimal::from(uplink_traffic_usage));
+        res.round().max(Decimal::ZERO).to_i64().unwrap_or(i64::MAX)
+    }
+}
+
+impl TryFrom<String> for CycleStatisticMethod {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let expr = expr_util::parse(&text)?;
+        Ok(CycleStatisticMethod { text, expr })
+    }
+}
+
+impl From<CycleStatisticMethod> for String {
+    fn from(method: CycleStatisticMethod) -> Self {
+        method.text
+    }
 }
 
+/// 月、年周期的重置日，Day 超过当月天数时取当月最后一天
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum MonthDay {
+    Day(u32),
+    Last,
+}
+
+/// 循环周期为 (每几个周期重置, 任意一次重置日期)，WEEK 的重置日期决定每周的重置星期
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CycleType {
     DAY(i64, chrono::NaiveDate),
-    MONTH(i64, chrono::NaiveDate),
+    WEEK(i64, chrono::NaiveDate),
+    MONTH(i64, chrono::NaiveDate, MonthDay),
+    YEAR(i64, chrono::NaiveDate, MonthDay),
     ONCE(chrono::NaiveDate, chrono::NaiveDate),
 }
 
+/// 计费方式，按流量: Volume  按 5 分钟采样的 95 计费带宽: Percentile95
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum CycleBillingMode {
+    Volume,
+    Percentile95,
+}
+
+/// 阈值的统计方向，计入流量: Total 上行: Uplink 下行: Downlink 超额费用: Cost 预计周期用量: Forecast
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum CycleNotifyTarget {
+    Total,
+    Uplink,
+    Downlink,
+    Cost,
+    Forecast,
+}
+
+/// 超额计费的一档价格，up_to 为累计超出量的上限(字节)，为空时不限
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CyclePricingTier {
+    pub up_to: Option<i64>,
+    pub price_per_gb: Decimal,
+}
+
+/// 超额计费，计入流量超出 included 的部分按阶梯价格累计
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CyclePricing {
+    pub currency: String,
+    pub included: i64,
+    pub tiers: Vec<CyclePricingTier>,
+}
+
+impl CyclePricing {
+    /// 计入流量对应的超额费用，保留两位小数，超出全部阶梯上限的部分按最后一档计算
+    pub fn overage_cost(&self, traffic_usage: i64) -> Decimal {
+        let gb = Decimal::from(1024 * 1024 * 1024);
+        let overage = std::cmp::max(traffic_usage - self.included, 0);
+        let mut cost = Decimal::ZERO;
+        let mut lower = 0;
+        for (i, tier) in self.tiers.iter().enumerate() {
+            let upper = match tier.up_to {
+                Some(up_to) if i + 1 < self.tiers.len() => std::cmp::min(up_to, overage),
+                _ => overage,
+            };
+            if upper > lower {
+                cost += Decimal::from(upper - lower) / gb * tier.price_per_gb;
+                lower = upper;
+            }
+        }
+        cost.round_dp(2)
+    }
+}
+
+/// 用量预测的基准，每个周期日计算一次，day 之前为已结束的天
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CycleForecast {
+    pub day: chrono::NaiveDate,
+    pub history_days: usize,
+    pub history_traffic_usage: i64,
+    /// day 之前的上下行用量，计算今天的计入流量时使用
+    pub history_uplink_traffic_usage: i64,
+    pub history_downlink_traffic_usage: i64,
+    /// day 到周期结束每天的预计计入流量
+    pub expected_list: Vec<i64>,
+}
+
+impl CycleForecast {
+    /// 今天的计入流量，对今天的上下行用量计算统计方法，max 这样的统计方法不能用累计的计入流量相减
+    pub fn today_traffic_usage(
+        &self,
+        statistic_method: &CycleStatisticMethod,
+        uplink_traffic_usage: i64,
+        downlink_traffic_usage: i64,
+    ) -> i64 {
+        statistic_method.traffic_usage(
+            std::cmp::max(uplink_traffic_usage - self.history_uplink_traffic_usage, 0),
+            std::cmp::max(downlink_traffic_usage - self.history_downlink_traffic_usage, 0),
+        )
+    }
+
+    /// 按当前计入流量和今天的计入流量预计周期结束时的用量，以及超出 traffic_limit 的日期，没有已结束的天时无法预计
+    pub fn project(
+        &self,
+        traffic_usage: i64,
+        today_traffic_usage: i64,
+        traffic_limit: Option<i64>,
+    ) -> Option<(i64, Option<chrono::NaiveDate>)> {
+        if self.history_days == 0 {
+            return None;
+        }
+        // 今天已用的流量超过预计时按实际用量计算
+        let mut projected = traffic_usage;
+        let mut exceed_date = None;
+        for (i, expected) in self.expected_list.iter().enumerate() {
+            projected += if i == 0 { std::cmp::max(expected - today_traffic_usage, 0) } else { *expected };
+            if exceed_date.is_none() && traffic_limit.is_some_and(|limit| projected > limit) {
+                exceed_date = Some(self.day + chrono::Duration::days(i as i64));
+            }
+        }
+        Some((projected, exceed_date))
+    }
+}
+
+/// 每日额度，allowance 为当天的每日额度，triggered_day 为已超过额度还没有执行 end_exec 的周期日
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CyclePacing {
+    pub multiple: Decimal,
+    pub exec: Vec<ActionConfig>,
+    pub end_exec: Vec<ActionConfig>,
+    pub allowance: Option<i64>,
+    pub triggered_day: Option<chrono::NaiveDate>,
+}
+
+/// 免费时段，小时所在的星期在 weekday 中且小时在 start_hour ~ end_hour 之间时免费，start_hour 大于 end_hour 时跨零点
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FreeWindow {
+    pub weekday: Vec<chrono::Weekday>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl FreeWindow {
+    pub fn contains(&self, weekday: chrono::Weekday, hour: u32) -> bool {
+        let in_hour = if self.start_hour < self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        };
+        in_hour && self.weekday.contains(&weekday)
+    }
+}
+
+/// 阈值，配置的条件全部满足时触发，percent 为 0 时不按百分比判断
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CycleNotifyAppState {
-    pub percent: u8,
+    pub percent: Decimal,
+    /// 已用量达到此值，字节
+    pub used: Option<i64>,
+    /// 剩余量不超过此值，字节
+    pub remaining: Option<i64>,
+    /// 周期剩余天数(含今天)不超过此值
+    pub remaining_days: Option<i64>,
+    pub target: CycleNotifyTarget,
+    pub cost: Option<Decimal>,
+    /// 条件的唯一标识，触发记录按统计方向和条件区分
+    pub condition: String,
     pub finished: bool,
-    pub exec: Option<String>,
+    pub exec: Vec<ActionConfig>,
+}
+
+impl CycleNotifyAppState {
+    /// 按统计方向的用量和限制判断百分比、已用量、剩余量条件是否全部满足
+    pub fn reached(&self, usage: i64, limit: i64) -> bool {
+        let percent_reached = self.percent <= Decimal::ZERO
+            || Decimal::from(usage) >= Decimal::from(limit) / Decimal::ONE_HUNDRED * self.percent;
+        percent_reached
+            && self.used.is_none_or(|used| usage >= used)
+            && self.remaining.is_none_or(|remaining| limit - usage <= remaining)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CycleAppState {
+    pub name: String,
     pub cycle_type: CycleType,
     pub current_cycle_start_date: chrono::NaiveDate,
     pub current_cycle_end_date: chrono::NaiveDate,
+    pub reset_hour: Option<u32>,
+    pub network_name: Vec<String>,
     pub uplink_traffic_usage: i64,
     pub downlink_traffic_usage: i64,
     pub traffic_usage: i64,
-    pub traffic_limit: i64,
+    pub free_uplink_traffic_usage: i64,
+    pub free_downlink_traffic_usage: i64,
+    pub traffic_limit: Option<i64>,
+    pub uplink_limit: Option<i64>,
+    pub downlink_limit: Option<i64>,
+    pub traffic_rollover: Option<i64>,
+    pub uplink_rollover: Option<i64>,
+    pub downlink_rollover: Option<i64>,
+    pub billing_mode: CycleBillingMode,
+    /// 承诺带宽，单位 bit/s
+    pub committed_rate: Option<i64>,
+    /// 周期内已结束的 5 分钟采样的 95 计费带宽，单位 bit/s
+    pub rate_p95: Option<i64>,
+    /// rate_p95 统计到的时间(UTC)，之后的采样还没有结束
+    pub rate_p95_time: Option<chrono::NaiveDateTime>,
+    pub pricing: Option<CyclePricing>,
+    /// 按计入流量累计的超额费用，没有配置超额计费时为空
+    pub overage_cost: Option<Decimal>,
+    pub forecast: Option<CycleForecast>,
+    /// 按日均用量和星期规律预计的周期结束时计入流量
+    pub projected_traffic_usage: Option<i64>,
+    /// 预计超出 traffic_limit 的日期
+    pub projected_exceed_date: Option<chrono::NaiveDate>,
+    pub pacing: Option<CyclePacing>,
     pub notify: Vec<CycleNotifyAppState>,
     pub statistic_method: CycleStatisticMethod,
+    pub free_windows: Vec<FreeWindow>,
+}
+
+impl CycleAppState {
+    /// UTC 时间所在的本地小时是否在免费时段内
+    pub fn is_free_time(&self, time: chrono::NaiveDateTime, tz: chrono_tz::Tz) -> bool {
+        let local = time_util::to_local(time, tz);
+        self.free_windows.iter().any(|ele| ele.contains(local.weekday(), local.hour()))
+    }
+}
+
+/// 实时速率，单位 字节/秒
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LiveRate {
+    pub network_name: String,
+    pub time: chrono::NaiveDateTime,
+    pub uplink_rate: i64,
+    pub downlink_rate: i64,
+}
+
+/// 实时速率只保存在内存中，不写入数据库
+pub struct LiveAppState {
+    pub buffer: RwLock<HashMap<String, VecDeque<LiveRate>>>,
+    pub sender: broadcast::Sender<LiveRate>,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: crate::config::app_config::Config,
     pub db_pool: Pool<Sqlite>,
+    pub traffic_source: Arc<dyn TrafficSource>,
 
-    pub cycle: Arc<RwLock<Option<CycleAppState>>>,
+    pub cycles: Arc<RwLock<Vec<CycleAppState>>>,
+    pub live: Arc<LiveAppState>,
+    /// 采集、汇总、手动修改数据都需要持有此锁，保证同一时间只有一次采集在读写统计数据和周期用量
+    pub collect_lock: Arc<Mutex<()>>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct AppStateDisplay {
     pub config: crate::config::app_config::Config,
-    pub cycle: Option<CycleAppState>,
+    pub cycles: Vec<CycleAppState>,
+}
+
+#[cfg(test)]
+mod state_test {
+    use chrono::{NaiveDate, Weekday};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const GB: i64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn notify_reached_test() {
+        let notify = CycleNotifyAppState {
+            percent: dec!(99.5),
+            used: None,
+            remaining: None,
+            remaining_days: None,
+            target: CycleNotifyTarget::Total,
+            cost: None,
+            condition: String::new(),
+            finished: false,
+            exec: vec![],
+        };
+        assert!(notify.reached(995, 1000));
+        assert!(!notify.reached(994, 1000));
+
+        let notify = CycleNotifyAppState { percent: dec!(0), used: Some(500), ..notify };
+        assert!(notify.reached(500, 1000));
+        assert!(!notify.reached(499, 1000));
+
+        let notify = CycleNotifyAppState { used: None, remaining: Some(200), ..notify };
+        assert!(!notify.reached(700, 1000));
+        assert!(notify.reached(800, 1000));
+
+        // 多个条件需要全部满足
+        let notify = CycleNotifyAppState { percent: dec!(70), ..notify };
+        assert!(!notify.reached(750, 1000));
+        assert!(notify.reached(800, 1000));
+
+        // 只有剩余天数条件时由调用方按天数判断
+        let notify = CycleNotifyAppState { percent: dec!(0), remaining: None, remaining_days: Some(5), ..notify };
+        assert!(notify.reached(0, 1000));
+    }
+
+    #[test]
+    fn free_window_test() {
+        let free_window = FreeWindow { weekday: vec![Weekday::Fri], start_hour: 22, end_hour: 2 };
+        assert!(free_window.contains(Weekday::Fri, 23));
+        assert!(free_window.contains(Weekday::Fri, 1));
+        assert!(!free_window.contains(Weekday::Fri, 2));
+        assert!(!free_window.contains(Weekday::Sat, 23));
+
+        let free_window = FreeWindow { weekday: vec![Weekday::Mon], start_hour: 0, end_hour: 8 };
+        assert!(free_window.contains(Weekday::Mon, 7));
+        assert!(!free_window.contains(Weekday::Mon, 8));
+    }
+
+    #[test]
+    fn overage_cost_test() {
+        let pricing = CyclePricing {
+            currency: "USD".to_string(),
+            included: 100 * GB,
+            tiers: vec![CyclePricingTier { up_to: None, price_per_gb: dec!(0.01) }],
+        };
+        assert_eq!(pricing.overage_cost(50 * GB), dec!(0));
+        assert_eq!(pricing.overage_cost(250 * GB), dec!(1.5));
+
+        // 超出的前 10GB 每 GB 1，之后 10GB 每 GB 0.5，再之后按最后一档 0.1
+        let pricing = CyclePricing {
+            currency: "USD".to_string(),
+            included: 1024 * GB,
+            tiers: vec![
+                CyclePricingTier { up_to: Some(10 * GB), price_per_gb: dec!(1) },
+                CyclePricingTier { up_to: Some(20 * GB), price_per_gb: dec!(0.5) },
+                CyclePricingTier { up_to: None, price_per_gb: dec!(0.1) },
+            ],
+        };
+        assert_eq!(pricing.overage_cost(1024 * GB + 5 * GB), dec!(5));
+        assert_eq!(pricing.overage_cost(1024 * GB + 15 * GB), dec!(12.5));
+        assert_eq!(pricing.overage_cost(1024 * GB + 120 * GB), dec!(25));
+    }
+
+    #[test]
+    fn forecast_test() {
+        let forecast = CycleForecast {
+            day: NaiveDate::from_ymd_opt(2024, 8, 19).unwrap(),
+            history_days: 14,
+            history_traffic_usage: 200 * GB,
+            history_uplink_traffic_usage: 50 * GB,
+            history_downlink_traffic_usage: 200 * GB,
+            expected_list: vec![10 * GB, 10 * GB, 10 * GB, 10 * GB, 10 * GB, 30 * GB, 30 * GB],
+        };
+        // 今天已用 4GB，按预计再用 6GB
+        assert_eq!(
+            forecast.project(204 * GB, 4 * GB, Some(300 * GB)),
+            Some((310 * GB, NaiveDate::from_ymd_opt(2024, 8, 25)))
+        );
+        // 今天已用超过预计时按实际用量
+        assert_eq!(
+            forecast.project(215 * GB, 15 * GB, Some(250 * GB)),
+            Some((315 * GB, NaiveDate::from_ymd_opt(2024, 8, 23)))
+        );
+        assert_eq!(forecast.project(204 * GB, 4 * GB, None), Some((310 * GB, None)));
+
+        // max(in,out) 时今天上行 8GB 被历史的下行掩盖，按今天的上下行计算
+        let max_method = CycleStatisticMethod::try_from("max(in,out)".to_string()).unwrap();
+        assert_eq!(forecast.today_traffic_usage(&max_method, 58 * GB, 202 * GB), 8 * GB);
+        let sum_method = CycleStatisticMethod::try_from("sum(in,out)".to_string()).unwrap();
+        assert_eq!(forecast.today_traffic_usage(&sum_method, 58 * GB, 202 * GB), 10 * GB);
+
+        let forecast = CycleForecast { history_days: 0, ..forecast };
+        assert_eq!(forecast.project(204 * GB, 4 * GB, None), None);
+    }
 }
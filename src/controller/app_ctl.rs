@@ -8,6 +8,6 @@ pub async fn version() -> impl IntoResponse {
 pub async fn state(State(app_state): State<AppState>) -> impl IntoResponse {
     ApiResponse::ok_data(AppStateDisplay {
         config: app_state.config.clone(),
-        cycle: app_state.cycle.read().await.clone(),
+        cycles: app_state.cycles.read().await.clone(),
     })
 }
@@ -1,12 +1,23 @@
 use crate::{
-    config::state::AppState, mapper::{monitor_day_mapper, monitor_hour_mapper, monitor_second_mapper::{self, MonitorSecond}}, service::statistics_svc, util::response_util::ApiResponse
+    config::state::AppState, mapper::{monitor_cycle_mapper, monitor_day_mapper, monitor_event_mapper, monitor_five_minute_mapper, monitor_hour_mapper, monitor_reset_mapper, monitor_second_mapper::{self, MonitorSecond}}, service::{live_svc, statistics_svc}, util::{response_util::ApiResponse, time_util}
 };
-use axum::{extract::State, response::IntoResponse, Json};
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModifyDataParam {
+    pub network_name: Option<String>,
     pub uplink_traffic_usage: i64,
     pub downlink_traffic_usage: i64,
 }
@@ -15,10 +26,20 @@ pub async fn modify_data(
     State(app_state): State<AppState>,
     body: Json<ModifyDataParam>,
 ) -> impl IntoResponse {
-    let now = chrono::Local::now().naive_local();
+    let network_name = match &body.network_name {
+        Some(network_name) => network_name.clone(),
+        None => app_state.config.network_name[0].clone(),
+    };
+    if !app_state.config.network_name.contains(&network_name) {
+        return ApiResponse::error(&format!("网卡 {} 不在监控列表中", network_name));
+    }
+    // 与采集任务互斥，避免同时读写小时、天数据和周期用量
+    let _collect_guard = app_state.collect_lock.lock().await;
+    let now = time_util::now();
     let data = MonitorSecond {
         id: None,
         create_time: None,
+        network_name: Some(network_name.clone()),
         start_time: Some(now),
         end_time: Some(now),
         uplink_traffic_readings: Some(0),
@@ -27,14 +48,20 @@ pub async fn modify_data(
         downlink_traffic_usage: Some(body.downlink_traffic_usage),
         time_interval: Some(0),
         is_corrected: Some(1),
+        boot_id: None,
+        ifindex: None,
+        is_estimated: Some(0),
+        ..Default::default()
     };
     match monitor_second_mapper::create(data, &app_state.db_pool).await {
         Ok(res) => {
-            let _ = statistics_svc::collect_hour_data(&app_state, now).await;
+            let _ = statistics_svc::collect_five_minute_data(&app_state, &network_name, now).await;
+
+            let _ = statistics_svc::collect_hour_data(&app_state, &network_name, now).await;
         
-            let _ = statistics_svc::collect_day_data(&app_state, now.date()).await;
+            let _ = statistics_svc::collect_day_data(&app_state, &network_name, time_util::to_local(now, app_state.config.tz()).date()).await;
 
-            let _ = statistics_svc::verify_exceeds_limit(&app_state, (body.uplink_traffic_usage, body.downlink_traffic_usage)).await;
+            let _ = statistics_svc::verify_exceeds_limit(&app_state, &network_name, &[(now, body.uplink_traffic_usage, body.downlink_traffic_usage)]).await;
         
             return ApiResponse::ok_data(res.rows_affected())
         },
@@ -44,6 +71,7 @@ pub async fn modify_data(
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PageMonitorDayParam {
+    network_name: Option<String>,
     start_date: String,
     end_date: String,
 }
@@ -60,7 +88,7 @@ pub async fn list_monitor_day(
         Ok(date) => date,
         Err(_) => return ApiResponse::error("结束日期格式错误"),
     };
-    match monitor_day_mapper::list_daterange_data(start_date, end_date, &app_state.db_pool).await {
+    match monitor_day_mapper::list_daterange_data(body.network_name.clone(), start_date, end_date, &app_state.db_pool).await {
         Ok(list) => return ApiResponse::ok_data(list),
         Err(e) => return ApiResponse::error(&format!("查询数据失败: {}", e)),
     }
@@ -68,6 +96,7 @@ pub async fn list_monitor_day(
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PageMonitorHourParam {
+    network_name: Option<String>,
     start_time: String,
     end_time: String,
 }
@@ -84,14 +113,55 @@ pub async fn list_monitor_hour(
         Ok(datetime) => datetime,
         Err(_) => return ApiResponse::error("结束时间格式错误"),
     };
-    match monitor_hour_mapper::list_timerange_data(start_time, end_time, &app_state.db_pool).await {
-        Ok(list) => return ApiResponse::ok_data(list),
-        Err(e) => return ApiResponse::error(&format!("查询数据失败: {}", e)),
+    // day hour 为配置时区的日期和小时，查询条件不需要转换
+    let tz = app_state.config.tz();
+    match monitor_hour_mapper::list_timerange_data(body.network_name.clone(), start_time, end_time, &app_state.db_pool).await {
+        Ok(mut list) => {
+            for ele in &mut list {
+                ele.start_time = ele.start_time.map(|time| time_util::to_local(time, tz));
+            }
+            ApiResponse::ok_data(list)
+        }
+        Err(e) => ApiResponse::error(&format!("查询数据失败: {}", e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageMonitorFiveMinuteParam {
+    network_name: Option<String>,
+    start_time: String,
+    end_time: String,
+}
+
+pub async fn list_monitor_five_minute(
+    State(app_state): State<AppState>,
+    body: Json<PageMonitorFiveMinuteParam>,
+) -> impl IntoResponse {
+    let start_time = match NaiveDateTime::parse_from_str(&body.start_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("开始时间格式错误"),
+    };
+    let end_time = match NaiveDateTime::parse_from_str(&body.end_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("结束时间格式错误"),
+    };
+    let tz = app_state.config.tz();
+    let (start_time, end_time) = (time_util::to_utc(start_time, tz), time_util::to_utc(end_time, tz));
+    match monitor_five_minute_mapper::list_timerange_data(body.network_name.clone(), start_time, end_time, &app_state.db_pool).await
+    {
+        Ok(mut list) => {
+            for ele in &mut list {
+                ele.start_time = ele.start_time.map(|time| time_util::to_local(time, tz));
+            }
+            ApiResponse::ok_data(list)
+        }
+        Err(e) => ApiResponse::error(&format!("查询数据失败: {}", e)),
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PageMonitorSecondParam {
+    network_name: Option<String>,
     start_time: String,
     end_time: String,
 }
@@ -108,21 +178,144 @@ pub async fn list_monitor_second(
         Ok(datetime) => datetime,
         Err(_) => return ApiResponse::error("结束时间格式错误"),
     };
-    match monitor_second_mapper::list_timerange_data(start_time, end_time, &app_state.db_pool).await
+    // 查询参数和返回结果均为配置时区的本地时间，数据库中为 UTC
+    let tz = app_state.config.tz();
+    let (start_time, end_time) = (time_util::to_utc(start_time, tz), time_util::to_utc(end_time, tz));
+    match monitor_second_mapper::list_timerange_data(body.network_name.clone(), start_time, end_time, &app_state.db_pool).await
     {
-        Ok(list) => return ApiResponse::ok_data(list),
+        Ok(mut list) => {
+            for ele in &mut list {
+                ele.start_time = ele.start_time.map(|time| time_util::to_local(time, tz));
+                ele.end_time = ele.end_time.map(|time| time_util::to_local(time, tz));
+            }
+            ApiResponse::ok_data(list)
+        }
         Err(e) => return ApiResponse::error(&format!("查询数据失败: {}", e)),
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageMonitorResetParam {
+    network_name: Option<String>,
+    start_time: String,
+    end_time: String,
+}
+
+pub async fn list_monitor_reset(
+    State(app_state): State<AppState>,
+    body: Json<PageMonitorResetParam>,
+) -> impl IntoResponse {
+    let start_time = match NaiveDateTime::parse_from_str(&body.start_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("开始时间格式错误"),
+    };
+    let end_time = match NaiveDateTime::parse_from_str(&body.end_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("结束时间格式错误"),
+    };
+    let tz = app_state.config.tz();
+    let (start_time, end_time) = (time_util::to_utc(start_time, tz), time_util::to_utc(end_time, tz));
+    match monitor_reset_mapper::list_timerange_data(body.network_name.clone(), start_time, end_time, &app_state.db_pool).await
+    {
+        Ok(mut list) => {
+            for ele in &mut list {
+                ele.reset_time = ele.reset_time.map(|time| time_util::to_local(time, tz));
+            }
+            ApiResponse::ok_data(list)
+        }
+        Err(e) => ApiResponse::error(&format!("查询数据失败: {}", e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageMonitorEventParam {
+    quota_name: Option<String>,
+    event: Option<String>,
+    start_time: String,
+    end_time: String,
+}
+
+/// 动作执行记录，按执行时间倒序
+pub async fn list_monitor_event(
+    State(app_state): State<AppState>,
+    body: Json<PageMonitorEventParam>,
+) -> impl IntoResponse {
+    let start_time = match NaiveDateTime::parse_from_str(&body.start_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("开始时间格式错误"),
+    };
+    let end_time = match NaiveDateTime::parse_from_str(&body.end_time, "%Y-%m-%dT%H:%M:%S") {
+        Ok(datetime) => datetime,
+        Err(_) => return ApiResponse::error("结束时间格式错误"),
+    };
+    let tz = app_state.config.tz();
+    let (start_time, end_time) = (time_util::to_utc(start_time, tz), time_util::to_utc(end_time, tz));
+    match monitor_event_mapper::list_timerange_data(
+        body.quota_name.clone(),
+        body.event.clone(),
+        start_time,
+        end_time,
+        &app_state.db_pool,
+    )
+    .await
+    {
+        Ok(mut list) => {
+            for ele in &mut list {
+                ele.start_time = ele.start_time.map(|time| time_util::to_local(time, tz));
+            }
+            ApiResponse::ok_data(list)
+        }
+        Err(e) => ApiResponse::error(&format!("查询数据失败: {}", e)),
+    }
+}
+
+/// 流量周期历史，按开始日期倒序
+pub async fn list_monitor_cycle(
+    State(app_state): State<AppState>,
+) -> impl IntoResponse {
+    match monitor_cycle_mapper::list_all(&app_state.db_pool).await {
+        Ok(list) => ApiResponse::ok_data(list),
+        Err(e) => ApiResponse::error(&format!("查询数据失败: {}", e)),
+    }
+}
+
 pub async fn send_today_statistics(
     State(app_state): State<AppState>,
 ) -> impl IntoResponse {
     if app_state.config.tg.is_none() {
         return ApiResponse::error("未配置 TG bot");
     }
-    match statistics_svc::tg_notify_daily_statistics(&app_state, chrono::Local::now().date_naive()).await {
+    match statistics_svc::tg_notify_daily_statistics(&app_state, time_util::today(app_state.config.tz())).await {
         Ok(()) => return ApiResponse::ok_data(()),
         Err(e) => return ApiResponse::error(&format!("发送消息失败: {}", e)),
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LiveParam {
+    network_name: Option<String>,
+}
+
+/// 实时速率推送，连接后先推送一次缓冲区中的历史数据(history)，之后每秒推送最新速率(rate)
+pub async fn live(
+    State(app_state): State<AppState>,
+    Query(param): Query<LiveParam>,
+) -> Response {
+    if app_state.config.web.as_ref().is_none_or(|web| web.live_buffer_secs == 0) {
+        return ApiResponse::<()>::error("未开启实时速率采集").into_response();
+    }
+    let receiver = app_state.live.sender.subscribe();
+    let history = live_svc::list(&app_state, param.network_name.as_deref()).await;
+    let history_stream = tokio_stream::once(Event::default().event("history").json_data(history));
+    let network_name = param.network_name;
+    let rate_stream = BroadcastStream::new(receiver).filter_map(move |res| match res {
+        Ok(rate) if network_name.as_ref().is_none_or(|network_name| network_name == &rate.network_name) => {
+            Some(Event::default().event("rate").json_data(rate))
+        }
+        _ => None,
+    });
+    let stream = history_stream
+        .chain(rate_stream)
+        .map(|event| Ok::<Event, Infallible>(event.unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))));
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
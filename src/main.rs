@@ -1,7 +1,7 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use config::state::AppState;
-use tokio::sync::RwLock;
+use config::state::{AppState, LiveAppState};
+use tokio::sync::{broadcast, Mutex, RwLock};
 // use service::systemstat_svc;
 
 mod config;
@@ -21,16 +21,26 @@ async fn main() -> anyhow::Result<()> {
 
     let db_pool = config::db::init().await?;
 
+    let traffic_source = service::traffic_source_svc::init(&config.traffic_source)?;
+
     let app_state = AppState {
         config: config,
         db_pool: db_pool,
-        cycle: Arc::new(RwLock::new(None)),
+        traffic_source,
+        cycles: Arc::new(RwLock::new(vec![])),
+        live: Arc::new(LiveAppState {
+            buffer: RwLock::new(HashMap::new()),
+            sender: broadcast::channel(64).0,
+        }),
+        collect_lock: Arc::new(Mutex::new(())),
     };
 
     service::statistics_svc::frist_collect(&app_state).await?;
 
     service::scheduler_svc::init(&app_state).await?;
 
+    service::live_svc::init(&app_state);
+
     // systemstat_svc::test();
 
     let app_state_clone = app_state.clone();
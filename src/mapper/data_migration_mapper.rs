@@ -0,0 +1,25 @@
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite, SqliteConnection};
+
+pub async fn exists(name: &str, pool: &Pool<Sqlite>) -> Result<bool, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new("select count(1) from data_migration where ");
+    query_builder.push("name = ").push_bind(name.to_string());
+    let query = query_builder.build_query_scalar::<i64>();
+    tracing::debug!("查询数据转换记录SQL: {}", query.sql());
+    let res = query.fetch_one(pool).await;
+    tracing::debug!("查询数据转换记录结果: {:?}", res);
+    res.map(|count| count > 0)
+}
+
+pub async fn create(
+    name: &str,
+    conn: &mut SqliteConnection,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into data_migration(name) values(");
+    query_builder.push_bind(name.to_string()).push(")");
+    let query = query_builder.build();
+    tracing::debug!("插入数据转换记录SQL: {}", query.sql());
+    let res = query.execute(conn).await;
+    tracing::debug!("插入数据转换记录结果: {:?}", res);
+    res
+}
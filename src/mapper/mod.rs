@@ -1,3 +1,9 @@
 pub mod monitor_second_mapper;
+pub mod monitor_five_minute_mapper;
 pub mod monitor_hour_mapper;
-pub mod monitor_day_mapper;
\ No newline at end of file
+pub mod monitor_day_mapper;
+pub mod monitor_notify_mapper;
+pub mod monitor_reset_mapper;
+pub mod monitor_cycle_mapper;
+pub mod data_migration_mapper;
+pub mod monitor_event_mapper;
@@ -0,0 +1,217 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+
+const ALL_FIELDS: &str = "id, create_time, quota_name, cycle_start_date, cycle_end_date, network_name, traffic_limit, uplink_limit, downlink_limit, traffic_rollover, uplink_rollover, downlink_rollover, billing_mode, committed_rate, rate_p95, statistic_method, uplink_traffic_usage, downlink_traffic_usage, traffic_usage, overage_cost, currency, free_uplink_traffic_usage, free_downlink_traffic_usage, notify_percent, exceeded";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct MonitorCycle {
+    pub id: Option<u32>,
+    pub create_time: Option<NaiveDateTime>,
+    pub quota_name: Option<String>,
+    pub cycle_start_date: Option<NaiveDate>,
+    pub cycle_end_date: Option<NaiveDate>,
+    pub network_name: Option<String>,
+    pub traffic_limit: Option<i64>,
+    pub uplink_limit: Option<i64>,
+    pub downlink_limit: Option<i64>,
+    pub traffic_rollover: Option<i64>,
+    pub uplink_rollover: Option<i64>,
+    pub downlink_rollover: Option<i64>,
+    pub billing_mode: Option<String>,
+    pub committed_rate: Option<i64>,
+    pub rate_p95: Option<i64>,
+    pub statistic_method: Option<String>,
+    pub uplink_traffic_usage: Option<i64>,
+    pub downlink_traffic_usage: Option<i64>,
+    pub traffic_usage: Option<i64>,
+    pub overage_cost: Option<String>,
+    pub currency: Option<String>,
+    pub free_uplink_traffic_usage: Option<i64>,
+    pub free_downlink_traffic_usage: Option<i64>,
+    pub notify_percent: Option<String>,
+    pub exceeded: Option<u32>,
+}
+
+pub async fn create(
+    entity: MonitorCycle,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_cycle(");
+    let mut separated = query_builder.separated(", ");
+    if entity.quota_name.is_some() {
+        separated.push("quota_name");
+    }
+    if entity.cycle_start_date.is_some() {
+        separated.push("cycle_start_date");
+    }
+    if entity.cycle_end_date.is_some() {
+        separated.push("cycle_end_date");
+    }
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
+    if entity.traffic_limit.is_some() {
+        separated.push("traffic_limit");
+    }
+    if entity.uplink_limit.is_some() {
+        separated.push("uplink_limit");
+    }
+    if entity.downlink_limit.is_some() {
+        separated.push("downlink_limit");
+    }
+    if entity.traffic_rollover.is_some() {
+        separated.push("traffic_rollover");
+    }
+    if entity.uplink_rollover.is_some() {
+        separated.push("uplink_rollover");
+    }
+    if entity.downlink_rollover.is_some() {
+        separated.push("downlink_rollover");
+    }
+    if entity.billing_mode.is_some() {
+        separated.push("billing_mode");
+    }
+    if entity.committed_rate.is_some() {
+        separated.push("committed_rate");
+    }
+    if entity.rate_p95.is_some() {
+        separated.push("rate_p95");
+    }
+    if entity.statistic_method.is_some() {
+        separated.push("statistic_method");
+    }
+    if entity.uplink_traffic_usage.is_some() {
+        separated.push("uplink_traffic_usage");
+    }
+    if entity.downlink_traffic_usage.is_some() {
+        separated.push("downlink_traffic_usage");
+    }
+    if entity.traffic_usage.is_some() {
+        separated.push("traffic_usage");
+    }
+    if entity.overage_cost.is_some() {
+        separated.push("overage_cost");
+    }
+    if entity.currency.is_some() {
+        separated.push("currency");
+    }
+    if entity.free_uplink_traffic_usage.is_some() {
+        separated.push("free_uplink_traffic_usage");
+    }
+    if entity.free_downlink_traffic_usage.is_some() {
+        separated.push("free_downlink_traffic_usage");
+    }
+    if entity.notify_percent.is_some() {
+        separated.push("notify_percent");
+    }
+    if entity.exceeded.is_some() {
+        separated.push("exceeded");
+    }
+    query_builder.push(")  values(");
+    let mut separated = query_builder.separated(", ");
+    if let Some(quota_name) = entity.quota_name {
+        separated.push_bind(quota_name);
+    }
+    if let Some(cycle_start_date) = entity.cycle_start_date {
+        separated.push_bind(cycle_start_date);
+    }
+    if let Some(cycle_end_date) = entity.cycle_end_date {
+        separated.push_bind(cycle_end_date);
+    }
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
+    if let Some(traffic_limit) = entity.traffic_limit {
+        separated.push_bind(traffic_limit);
+    }
+    if let Some(uplink_limit) = entity.uplink_limit {
+        separated.push_bind(uplink_limit);
+    }
+    if let Some(downlink_limit) = entity.downlink_limit {
+        separated.push_bind(downlink_limit);
+    }
+    if let Some(traffic_rollover) = entity.traffic_rollover {
+        separated.push_bind(traffic_rollover);
+    }
+    if let Some(uplink_rollover) = entity.uplink_rollover {
+        separated.push_bind(uplink_rollover);
+    }
+    if let Some(downlink_rollover) = entity.downlink_rollover {
+        separated.push_bind(downlink_rollover);
+    }
+    if let Some(billing_mode) = entity.billing_mode {
+        separated.push_bind(billing_mode);
+    }
+    if let Some(committed_rate) = entity.committed_rate {
+        separated.push_bind(committed_rate);
+    }
+    if let Some(rate_p95) = entity.rate_p95 {
+        separated.push_bind(rate_p95);
+    }
+    if let Some(statistic_method) = entity.statistic_method {
+        separated.push_bind(statistic_method);
+    }
+    if let Some(uplink_traffic_usage) = entity.uplink_traffic_usage {
+        separated.push_bind(uplink_traffic_usage);
+    }
+    if let Some(downlink_traffic_usage) = entity.downlink_traffic_usage {
+        separated.push_bind(downlink_traffic_usage);
+    }
+    if let Some(traffic_usage) = entity.traffic_usage {
+        separated.push_bind(traffic_usage);
+    }
+    if let Some(overage_cost) = entity.overage_cost {
+        separated.push_bind(overage_cost);
+    }
+    if let Some(currency) = entity.currency {
+        separated.push_bind(currency);
+    }
+    if let Some(free_uplink_traffic_usage) = entity.free_uplink_traffic_usage {
+        separated.push_bind(free_uplink_traffic_usage);
+    }
+    if let Some(free_downlink_traffic_usage) = entity.free_downlink_traffic_usage {
+        separated.push_bind(free_downlink_traffic_usage);
+    }
+    if let Some(notify_percent) = entity.notify_percent {
+        separated.push_bind(notify_percent);
+    }
+    if let Some(exceeded) = entity.exceeded {
+        separated.push_bind(exceeded);
+    }
+    query_builder.push(")");
+
+    let query = query_builder.build();
+    tracing::debug!("插入流量周期历史SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("插入流量周期历史结果: {:?}", res);
+    res
+}
+
+pub async fn get_cycle_data(
+    quota_name: &str,
+    cycle_start_date: NaiveDate,
+    cycle_end_date: NaiveDate,
+    pool: &Pool<Sqlite>,
+) -> Result<Option<MonitorCycle>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_cycle where ", ALL_FIELDS));
+    query_builder.push("quota_name = ").push_bind(quota_name.to_string());
+    query_builder.push(" and cycle_start_date = ").push_bind(cycle_start_date);
+    query_builder.push(" and cycle_end_date = ").push_bind(cycle_end_date);
+    let query = query_builder.build_query_as::<MonitorCycle>();
+    tracing::debug!("查询流量周期历史SQL: {}", query.sql());
+    let res = query.fetch_optional(pool).await;
+    tracing::debug!("查询流量周期历史结果: {:?}", res);
+    res
+}
+
+pub async fn list_all(pool: &Pool<Sqlite>) -> Result<Vec<MonitorCycle>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_cycle order by cycle_start_date desc, quota_name", ALL_FIELDS));
+    let query = query_builder.build_query_as::<MonitorCycle>();
+    tracing::debug!("查询全部流量周期历史SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("查询全部流量周期历史结果: {:?}", res);
+    res
+}
@@ -2,15 +2,23 @@ use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
 
-const ALL_FIELDS: &str = "id, create_time, day, uplink_traffic_usage, downlink_traffic_usage";
+const ALL_FIELDS: &str = "id, create_time, network_name, day, uplink_traffic_usage, downlink_traffic_usage, is_estimated, uplink_packets_usage, downlink_packets_usage, uplink_errors_usage, downlink_errors_usage, uplink_drops_usage, downlink_drops_usage";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
 pub struct MonitorDay {
     pub id: Option<u32>,
     pub create_time: Option<NaiveDateTime>,
+    pub network_name: Option<String>,
     pub day: Option<NaiveDate>,
     pub uplink_traffic_usage: Option<i64>,
     pub downlink_traffic_usage: Option<i64>,
+    pub is_estimated: Option<u32>,
+    pub uplink_packets_usage: Option<i64>,
+    pub downlink_packets_usage: Option<i64>,
+    pub uplink_errors_usage: Option<i64>,
+    pub downlink_errors_usage: Option<i64>,
+    pub uplink_drops_usage: Option<i64>,
+    pub downlink_drops_usage: Option<i64>,
 }
 
 pub async fn update(
@@ -19,6 +27,9 @@ pub async fn update(
 ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_day set ");
     let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push("network_name = ").push_bind_unseparated(network_name);
+    }
     if entity.day.is_some() {
         separated.push("day = ").push_bind_unseparated(entity.day.unwrap());
     }
@@ -28,6 +39,27 @@ pub async fn update(
     if entity.downlink_traffic_usage.is_some() {
         separated.push("downlink_traffic_usage = ").push_bind_unseparated(entity.downlink_traffic_usage.unwrap());
     }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push("is_estimated = ").push_bind_unseparated(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push("uplink_packets_usage = ").push_bind_unseparated(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push("downlink_packets_usage = ").push_bind_unseparated(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push("uplink_errors_usage = ").push_bind_unseparated(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push("downlink_errors_usage = ").push_bind_unseparated(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push("uplink_drops_usage = ").push_bind_unseparated(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push("downlink_drops_usage = ").push_bind_unseparated(downlink_drops_usage);
+    }
     query_builder.push(" where id = ").push_bind(entity.id.unwrap());
 
     let query = query_builder.build();
@@ -43,6 +75,9 @@ pub async fn create(
 ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_day(");
     let mut separated = query_builder.separated(", ");
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
     if entity.day.is_some() {
         separated.push("day");
     }
@@ -52,8 +87,32 @@ pub async fn create(
     if entity.downlink_traffic_usage.is_some() {
         separated.push("downlink_traffic_usage");
     }
+    if entity.is_estimated.is_some() {
+        separated.push("is_estimated");
+    }
+    if entity.uplink_packets_usage.is_some() {
+        separated.push("uplink_packets_usage");
+    }
+    if entity.downlink_packets_usage.is_some() {
+        separated.push("downlink_packets_usage");
+    }
+    if entity.uplink_errors_usage.is_some() {
+        separated.push("uplink_errors_usage");
+    }
+    if entity.downlink_errors_usage.is_some() {
+        separated.push("downlink_errors_usage");
+    }
+    if entity.uplink_drops_usage.is_some() {
+        separated.push("uplink_drops_usage");
+    }
+    if entity.downlink_drops_usage.is_some() {
+        separated.push("downlink_drops_usage");
+    }
     query_builder.push(")  values(");
     let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
     if entity.day.is_some() {
         separated.push_bind(entity.day.unwrap());
     }
@@ -63,6 +122,27 @@ pub async fn create(
     if entity.downlink_traffic_usage.is_some() {
         separated.push_bind(entity.downlink_traffic_usage.unwrap());
     }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push_bind(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push_bind(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push_bind(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push_bind(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push_bind(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push_bind(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push_bind(downlink_drops_usage);
+    }
     query_builder.push(")");
 
     let query = query_builder.build();
@@ -73,6 +153,7 @@ pub async fn create(
 }
 
 pub async fn sum_daterange_data(
+    network_name_list: &[String],
     start_date: NaiveDate,
     end_date: NaiveDate,
     pool: &Pool<Sqlite>,
@@ -82,6 +163,12 @@ pub async fn sum_daterange_data(
     );
     query_builder.push("day >= ").push_bind(start_date);
     query_builder.push(" and day <= ").push_bind(end_date);
+    query_builder.push(" and network_name in (");
+    let mut separated = query_builder.separated(", ");
+    for network_name in network_name_list {
+        separated.push_bind(network_name.clone());
+    }
+    separated.push_unseparated(")");
     let query = query_builder.build_query_as::<(i64, i64)>();
     tracing::debug!("查询区域天监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
@@ -90,12 +177,14 @@ pub async fn sum_daterange_data(
 }
 
 pub async fn get_day_data(
+    network_name: &str,
     day: NaiveDate,
     pool: &Pool<Sqlite>,
 ) -> Result<Option<MonitorDay>, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> =
         QueryBuilder::new(format!("select {} from monitor_day where ", ALL_FIELDS));
-    query_builder.push("day = ").push_bind(day);
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" and day = ").push_bind(day);
     let query = query_builder.build_query_as::<MonitorDay>();
     tracing::debug!("查询区域天监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
@@ -104,6 +193,7 @@ pub async fn get_day_data(
 }
 
 pub async fn list_daterange_data(
+    network_name: Option<String>,
     start_date: NaiveDate,
     end_date: NaiveDate,
     pool: &Pool<Sqlite>,
@@ -112,9 +202,39 @@ pub async fn list_daterange_data(
         QueryBuilder::new(format!("select {} from monitor_day where ", ALL_FIELDS));
     query_builder.push("day >= ").push_bind(start_date);
     query_builder.push(" and day <= ").push_bind(end_date);
+    if let Some(network_name) = network_name {
+        query_builder.push(" and network_name = ").push_bind(network_name);
+    }
     let query = query_builder.build_query_as::<MonitorDay>();
     tracing::debug!("查询区域天监控数据SQL: {}", query.sql());
     let res = query.fetch_all(pool).await;
     tracing::debug!("查询区域天监控数据结果: {:?}", res);
     res
 }
+
+pub async fn delete_by_date(
+    day: NaiveDate,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder = QueryBuilder::new("delete from monitor_day where ");
+    query_builder.push("day < ").push_bind(day);
+    let query = query_builder.build();
+    tracing::debug!("删除天监控数据SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("删除天监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn fill_network_name(
+    network_name: &str,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_day set ");
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" where network_name = ''");
+    let query = query_builder.build();
+    tracing::debug!("补全天监控数据网卡名称SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("补全天监控数据网卡名称结果: {:?}", res);
+    res
+}
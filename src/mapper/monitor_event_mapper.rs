@@ -0,0 +1,134 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+
+const ALL_FIELDS: &str = "id, create_time, quota_name, event, target, condition, action_type, action, success, attempts, output, start_time, duration_ms";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct MonitorEvent {
+    pub id: Option<u32>,
+    pub create_time: Option<NaiveDateTime>,
+    pub quota_name: Option<String>,
+    pub event: Option<String>,
+    pub target: Option<String>,
+    pub condition: Option<String>,
+    pub action_type: Option<String>,
+    pub action: Option<String>,
+    pub success: Option<u32>,
+    pub attempts: Option<u32>,
+    pub output: Option<String>,
+    pub start_time: Option<NaiveDateTime>,
+    pub duration_ms: Option<i64>,
+}
+
+pub async fn create(
+    entity: MonitorEvent,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_event(");
+    let mut separated = query_builder.separated(", ");
+    if entity.quota_name.is_some() {
+        separated.push("quota_name");
+    }
+    if entity.event.is_some() {
+        separated.push("event");
+    }
+    if entity.target.is_some() {
+        separated.push("target");
+    }
+    if entity.condition.is_some() {
+        separated.push("condition");
+    }
+    if entity.action_type.is_some() {
+        separated.push("action_type");
+    }
+    if entity.action.is_some() {
+        separated.push("action");
+    }
+    if entity.success.is_some() {
+        separated.push("success");
+    }
+    if entity.attempts.is_some() {
+        separated.push("attempts");
+    }
+    if entity.output.is_some() {
+        separated.push("output");
+    }
+    if entity.start_time.is_some() {
+        separated.push("start_time");
+    }
+    if entity.duration_ms.is_some() {
+        separated.push("duration_ms");
+    }
+    query_builder.push(")  values(");
+    let mut separated = query_builder.separated(", ");
+    if let Some(quota_name) = entity.quota_name {
+        separated.push_bind(quota_name);
+    }
+    if let Some(event) = entity.event {
+        separated.push_bind(event);
+    }
+    if let Some(target) = entity.target {
+        separated.push_bind(target);
+    }
+    if let Some(condition) = entity.condition {
+        separated.push_bind(condition);
+    }
+    if let Some(action_type) = entity.action_type {
+        separated.push_bind(action_type);
+    }
+    if let Some(action) = entity.action {
+        separated.push_bind(action);
+    }
+    if let Some(success) = entity.success {
+        separated.push_bind(success);
+    }
+    if let Some(attempts) = entity.attempts {
+        separated.push_bind(attempts);
+    }
+    if let Some(output) = entity.output {
+        separated.push_bind(output);
+    }
+    if let Some(start_time) = entity.start_time {
+        separated.push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    if let Some(duration_ms) = entity.duration_ms {
+        separated.push_bind(duration_ms);
+    }
+    query_builder.push(")");
+
+    let query = query_builder.build();
+    tracing::debug!("插入动作执行记录SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("插入动作执行记录结果: {:?}", res);
+    res
+}
+
+pub async fn list_timerange_data(
+    quota_name: Option<String>,
+    event: Option<String>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<MonitorEvent>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_event where ", ALL_FIELDS));
+    query_builder
+        .push("start_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and start_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    if let Some(quota_name) = quota_name {
+        query_builder.push(" and quota_name = ").push_bind(quota_name);
+    }
+    if let Some(event) = event {
+        query_builder.push(" and event = ").push_bind(event);
+    }
+    query_builder.push(" order by id desc");
+    let query = query_builder.build_query_as::<MonitorEvent>();
+    tracing::debug!("查询动作执行记录SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("查询动作执行记录结果: {:?}", res);
+    res
+}
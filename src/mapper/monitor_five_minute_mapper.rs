@@ -0,0 +1,244 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+
+const ALL_FIELDS: &str = "id, create_time, network_name, start_time, uplink_traffic_usage, downlink_traffic_usage, is_estimated, uplink_packets_usage, downlink_packets_usage, uplink_errors_usage, downlink_errors_usage, uplink_drops_usage, downlink_drops_usage";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct MonitorFiveMinute {
+    pub id: Option<u32>,
+    pub create_time: Option<NaiveDateTime>,
+    pub network_name: Option<String>,
+    pub start_time: Option<NaiveDateTime>,
+    pub uplink_traffic_usage: Option<i64>,
+    pub downlink_traffic_usage: Option<i64>,
+    pub is_estimated: Option<u32>,
+    pub uplink_packets_usage: Option<i64>,
+    pub downlink_packets_usage: Option<i64>,
+    pub uplink_errors_usage: Option<i64>,
+    pub downlink_errors_usage: Option<i64>,
+    pub uplink_drops_usage: Option<i64>,
+    pub downlink_drops_usage: Option<i64>,
+}
+
+pub async fn update(
+    entity: MonitorFiveMinute,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_five_minute set ");
+    let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push("network_name = ").push_bind_unseparated(network_name);
+    }
+    if let Some(start_time) = entity.start_time {
+        separated.push("start_time = ").push_bind_unseparated(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    if let Some(uplink_traffic_usage) = entity.uplink_traffic_usage {
+        separated.push("uplink_traffic_usage = ").push_bind_unseparated(uplink_traffic_usage);
+    }
+    if let Some(downlink_traffic_usage) = entity.downlink_traffic_usage {
+        separated.push("downlink_traffic_usage = ").push_bind_unseparated(downlink_traffic_usage);
+    }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push("is_estimated = ").push_bind_unseparated(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push("uplink_packets_usage = ").push_bind_unseparated(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push("downlink_packets_usage = ").push_bind_unseparated(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push("uplink_errors_usage = ").push_bind_unseparated(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push("downlink_errors_usage = ").push_bind_unseparated(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push("uplink_drops_usage = ").push_bind_unseparated(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push("downlink_drops_usage = ").push_bind_unseparated(downlink_drops_usage);
+    }
+    query_builder.push(" where id = ").push_bind(entity.id.unwrap());
+
+    let query = query_builder.build();
+    tracing::debug!("更新5分钟监控数据SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("更新5分钟监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn create(
+    entity: MonitorFiveMinute,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_five_minute(");
+    let mut separated = query_builder.separated(", ");
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
+    if entity.start_time.is_some() {
+        separated.push("start_time");
+    }
+    if entity.uplink_traffic_usage.is_some() {
+        separated.push("uplink_traffic_usage");
+    }
+    if entity.downlink_traffic_usage.is_some() {
+        separated.push("downlink_traffic_usage");
+    }
+    if entity.is_estimated.is_some() {
+        separated.push("is_estimated");
+    }
+    if entity.uplink_packets_usage.is_some() {
+        separated.push("uplink_packets_usage");
+    }
+    if entity.downlink_packets_usage.is_some() {
+        separated.push("downlink_packets_usage");
+    }
+    if entity.uplink_errors_usage.is_some() {
+        separated.push("uplink_errors_usage");
+    }
+    if entity.downlink_errors_usage.is_some() {
+        separated.push("downlink_errors_usage");
+    }
+    if entity.uplink_drops_usage.is_some() {
+        separated.push("uplink_drops_usage");
+    }
+    if entity.downlink_drops_usage.is_some() {
+        separated.push("downlink_drops_usage");
+    }
+    query_builder.push(")  values(");
+    let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
+    if let Some(start_time) = entity.start_time {
+        separated.push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    if let Some(uplink_traffic_usage) = entity.uplink_traffic_usage {
+        separated.push_bind(uplink_traffic_usage);
+    }
+    if let Some(downlink_traffic_usage) = entity.downlink_traffic_usage {
+        separated.push_bind(downlink_traffic_usage);
+    }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push_bind(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push_bind(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push_bind(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push_bind(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push_bind(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push_bind(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push_bind(downlink_drops_usage);
+    }
+    query_builder.push(")");
+
+    let query = query_builder.build();
+    tracing::debug!("插入5分钟监控数据SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("插入5分钟监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn get_data(
+    network_name: &str,
+    start_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Option<MonitorFiveMinute>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        format!("select {} from monitor_five_minute where ", ALL_FIELDS),
+    );
+    query_builder
+        .push("network_name = ")
+        .push_bind(network_name.to_string())
+        .push(" and start_time = ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    let query = query_builder.build_query_as::<MonitorFiveMinute>();
+    tracing::debug!("查询5分钟监控数据SQL: {}", query.sql());
+    let res = query.fetch_optional(pool).await;
+    tracing::debug!("查询5分钟监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn list_timerange_data(
+    network_name: Option<String>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<MonitorFiveMinute>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        format!("select {} from monitor_five_minute where ", ALL_FIELDS),
+    );
+    query_builder
+        .push("start_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and start_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    if let Some(network_name) = network_name {
+        query_builder.push(" and network_name = ").push_bind(network_name);
+    }
+    query_builder.push(" order by start_time");
+    let query = query_builder.build_query_as::<MonitorFiveMinute>();
+    tracing::debug!("查询区域5分钟监控数据SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("查询区域5分钟监控数据结果: {:?}", res);
+    res
+}
+
+/// 按 5 分钟汇总多个网卡在时间范围内的流量，返回 (开始时间, 上行, 下行, 是否包含估算数据)
+pub async fn sum_timerange_group_by_time(
+    network_name_list: &[String],
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<(NaiveDateTime, i64, i64, bool)>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "select start_time, sum(uplink_traffic_usage), sum(downlink_traffic_usage), max(is_estimated) from monitor_five_minute where ",
+    );
+    query_builder
+        .push("start_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and start_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" and network_name in (");
+    let mut separated = query_builder.separated(", ");
+    for network_name in network_name_list {
+        separated.push_bind(network_name.clone());
+    }
+    separated.push_unseparated(")");
+    query_builder.push(" group by start_time");
+    let query = query_builder.build_query_as::<(NaiveDateTime, i64, i64, bool)>();
+    tracing::debug!("按5分钟汇总区域监控数据SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("按5分钟汇总区域监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn delete_by_date(
+    date: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder = QueryBuilder::new("delete from monitor_five_minute where ");
+    query_builder
+        .push("start_time < ")
+        .push_bind(date.format("%Y-%m-%dT%H:%M:%S").to_string());
+    let query = query_builder.build();
+    tracing::debug!("删除5分钟监控数据SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("删除5分钟监控数据结果: {:?}", res);
+    res
+}
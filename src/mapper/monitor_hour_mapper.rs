@@ -1,17 +1,28 @@
 use chrono::{NaiveDate, NaiveDateTime, Timelike};
 use serde::{Deserialize, Serialize};
-use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite, SqliteConnection};
 
-const ALL_FIELDS: &str = "id, create_time, day, hour, uplink_traffic_usage, downlink_traffic_usage";
+use crate::mapper::monitor_second_mapper::{PacketsUsage, PACKETS_USAGE_SUM_FIELDS};
+
+const ALL_FIELDS: &str = "id, create_time, network_name, start_time, day, hour, uplink_traffic_usage, downlink_traffic_usage, is_estimated, uplink_packets_usage, downlink_packets_usage, uplink_errors_usage, downlink_errors_usage, uplink_drops_usage, downlink_drops_usage";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
 pub struct MonitorHour {
     pub id: Option<u32>,
     pub create_time: Option<NaiveDateTime>,
+    pub network_name: Option<String>,
+    pub start_time: Option<NaiveDateTime>,
     pub day: Option<NaiveDate>,
     pub hour: Option<u32>,
     pub uplink_traffic_usage: Option<i64>,
     pub downlink_traffic_usage: Option<i64>,
+    pub is_estimated: Option<u32>,
+    pub uplink_packets_usage: Option<i64>,
+    pub downlink_packets_usage: Option<i64>,
+    pub uplink_errors_usage: Option<i64>,
+    pub downlink_errors_usage: Option<i64>,
+    pub uplink_drops_usage: Option<i64>,
+    pub downlink_drops_usage: Option<i64>,
 }
 
 pub async fn update(
@@ -20,6 +31,12 @@ pub async fn update(
 ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_hour set ");
     let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push("network_name = ").push_bind_unseparated(network_name);
+    }
+    if let Some(start_time) = entity.start_time {
+        separated.push("start_time = ").push_bind_unseparated(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
     if entity.day.is_some() {
         separated.push("day = ").push_bind_unseparated(entity.day.unwrap());
     }
@@ -32,6 +49,27 @@ pub async fn update(
     if entity.downlink_traffic_usage.is_some() {
         separated.push("downlink_traffic_usage = ").push_bind_unseparated(entity.downlink_traffic_usage.unwrap());
     }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push("is_estimated = ").push_bind_unseparated(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push("uplink_packets_usage = ").push_bind_unseparated(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push("downlink_packets_usage = ").push_bind_unseparated(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push("uplink_errors_usage = ").push_bind_unseparated(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push("downlink_errors_usage = ").push_bind_unseparated(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push("uplink_drops_usage = ").push_bind_unseparated(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push("downlink_drops_usage = ").push_bind_unseparated(downlink_drops_usage);
+    }
     query_builder.push(" where id = ").push_bind(entity.id.unwrap());
 
     let query = query_builder.build();
@@ -47,6 +85,12 @@ pub async fn create(
 ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_hour(");
     let mut separated = query_builder.separated(", ");
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
+    if entity.start_time.is_some() {
+        separated.push("start_time");
+    }
     if entity.day.is_some() {
         separated.push("day");
     }
@@ -59,8 +103,35 @@ pub async fn create(
     if entity.downlink_traffic_usage.is_some() {
         separated.push("downlink_traffic_usage");
     }
+    if entity.is_estimated.is_some() {
+        separated.push("is_estimated");
+    }
+    if entity.uplink_packets_usage.is_some() {
+        separated.push("uplink_packets_usage");
+    }
+    if entity.downlink_packets_usage.is_some() {
+        separated.push("downlink_packets_usage");
+    }
+    if entity.uplink_errors_usage.is_some() {
+        separated.push("uplink_errors_usage");
+    }
+    if entity.downlink_errors_usage.is_some() {
+        separated.push("downlink_errors_usage");
+    }
+    if entity.uplink_drops_usage.is_some() {
+        separated.push("uplink_drops_usage");
+    }
+    if entity.downlink_drops_usage.is_some() {
+        separated.push("downlink_drops_usage");
+    }
     query_builder.push(")  values(");
     let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
+    if let Some(start_time) = entity.start_time {
+        separated.push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
     if entity.day.is_some() {
         separated.push_bind(entity.day.unwrap());
     }
@@ -73,6 +144,27 @@ pub async fn create(
     if entity.downlink_traffic_usage.is_some() {
         separated.push_bind(entity.downlink_traffic_usage.unwrap());
     }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push_bind(is_estimated);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push_bind(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push_bind(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push_bind(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push_bind(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push_bind(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push_bind(downlink_drops_usage);
+    }
     query_builder.push(")");
 
     let query = query_builder.build();
@@ -83,6 +175,7 @@ pub async fn create(
 }
 
 pub async fn sum_day_data(
+    network_name_list: &[String],
     day: NaiveDate,
     pool: &Pool<Sqlite>,
 ) -> Result<Option<(i64, i64)>, sqlx::Error> {
@@ -90,6 +183,12 @@ pub async fn sum_day_data(
         "select sum(uplink_traffic_usage), sum(downlink_traffic_usage) from monitor_hour where ",
     );
     query_builder.push("day = ").push_bind(day);
+    query_builder.push(" and network_name in (");
+    let mut separated = query_builder.separated(", ");
+    for network_name in network_name_list {
+        separated.push_bind(network_name.clone());
+    }
+    separated.push_unseparated(")");
     let query = query_builder.build_query_as::<(i64, i64)>();
     tracing::debug!("查询一天的小时监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
@@ -97,23 +196,89 @@ pub async fn sum_day_data(
     res
 }
 
-pub async fn get_day_hour_data(
+/// 按小时汇总多个网卡在时间范围内的流量，start_time 为 UTC，返回 (日期, 小时, 上行, 下行)
+pub async fn sum_timerange_group_by_hour(
+    network_name_list: &[String],
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<(NaiveDate, u32, i64, i64)>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "select day, hour, sum(uplink_traffic_usage), sum(downlink_traffic_usage) from monitor_hour where ",
+    );
+    query_builder.push("start_time >= ").push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" and start_time < ").push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" and network_name in (");
+    let mut separated = query_builder.separated(", ");
+    for network_name in network_name_list {
+        separated.push_bind(network_name.clone());
+    }
+    separated.push_unseparated(")");
+    query_builder.push(" group by day, hour");
+    let query = query_builder.build_query_as::<(NaiveDate, u32, i64, i64)>();
+    tracing::debug!("按小时汇总区域监控数据SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("按小时汇总区域监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn sum_day_packets_data(
+    network_name: &str,
+    day: NaiveDate,
+    pool: &Pool<Sqlite>,
+) -> Result<PacketsUsage, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+        "select {} from monitor_hour where ",
+        PACKETS_USAGE_SUM_FIELDS
+    ));
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" and day = ").push_bind(day);
+    let query = query_builder.build_query_as::<PacketsUsage>();
+    tracing::debug!("查询一天的小时包数据SQL: {}", query.sql());
+    let res = query.fetch_one(pool).await;
+    tracing::debug!("查询一天的小时包数据结果: {:?}", res);
+    res
+}
+
+pub async fn exists_estimated_day_data(
+    network_name: &str,
     day: NaiveDate,
-    hour: u32,
+    pool: &Pool<Sqlite>,
+) -> Result<bool, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "select count(1) from monitor_hour where is_estimated = 1 and ",
+    );
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" and day = ").push_bind(day);
+    let query = query_builder.build_query_scalar::<i64>();
+    tracing::debug!("查询一天的小时估算数据SQL: {}", query.sql());
+    let res = query.fetch_one(pool).await;
+    tracing::debug!("查询一天的小时估算数据结果: {:?}", res);
+    res.map(|count| count > 0)
+}
+
+pub async fn get_hour_data(
+    network_name: &str,
+    start_time: NaiveDateTime,
     pool: &Pool<Sqlite>,
 ) -> Result<Option<MonitorHour>, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
         format!("select {} from monitor_hour where ", ALL_FIELDS),
     );
-    query_builder.push("day = ").push_bind(day).push(" and hour = ").push_bind(hour);
+    query_builder
+        .push("network_name = ")
+        .push_bind(network_name.to_string())
+        .push(" and start_time = ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
     let query = query_builder.build_query_as::<MonitorHour>();
-    tracing::debug!("查询一天的小时监控数据SQL: {}", query.sql());
+    tracing::debug!("查询小时监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
-    tracing::debug!("查询一天的小时监控数据结果: {:?}", res);
+    tracing::debug!("查询小时监控数据结果: {:?}", res);
     res
 }
 
 pub async fn list_timerange_data(
+    network_name: Option<String>,
     start_time: NaiveDateTime,
     end_time: NaiveDateTime,
     pool: &Pool<Sqlite>,
@@ -121,12 +286,116 @@ pub async fn list_timerange_data(
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
         format!("select {} from monitor_hour where ", ALL_FIELDS),
     );
-    query_builder.push(" (day = ").push_bind(start_time.date()).push(" and hour >= ").push_bind(start_time.hour()).push(")");
+    if let Some(network_name) = network_name {
+        query_builder.push("network_name = ").push_bind(network_name).push(" and ");
+    }
+    query_builder.push("((day = ").push_bind(start_time.date()).push(" and hour >= ").push_bind(start_time.hour()).push(")");
     query_builder.push(" or (day > ").push_bind(start_time.date()).push(" and day < ").push_bind(end_time.date()).push(")");
-    query_builder.push(" or (day = ").push_bind(end_time.date()).push(" and hour <= ").push_bind(end_time.hour()).push(")");
+    query_builder.push(" or (day = ").push_bind(end_time.date()).push(" and hour <= ").push_bind(end_time.hour()).push("))");
     let query = query_builder.build_query_as::<MonitorHour>();
     tracing::debug!("查询一天的小时监控数据SQL: {}", query.sql());
     let res = query.fetch_all(pool).await;
     tracing::debug!("查询一天的小时监控数据结果: {:?}", res);
     res
 }
+
+pub async fn delete_by_date(
+    day: NaiveDate,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder = QueryBuilder::new("delete from monitor_hour where ");
+    query_builder.push("day < ").push_bind(day);
+    let query = query_builder.build();
+    tracing::debug!("删除小时监控数据SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("删除小时监控数据结果: {:?}", res);
+    res
+}
+
+pub async fn fill_network_name(
+    network_name: &str,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_hour set ");
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" where network_name = ''");
+    let query = query_builder.build();
+    tracing::debug!("补全小时监控数据网卡名称SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("补全小时监控数据网卡名称结果: {:?}", res);
+    res
+}
+
+pub async fn list_without_start_time(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<MonitorHour>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        format!("select {} from monitor_hour where start_time is null", ALL_FIELDS),
+    );
+    let query = query_builder.build_query_as::<MonitorHour>();
+    tracing::debug!("查询没有开始时间的小时监控数据SQL: {}", query.sql());
+    let res = query.fetch_all(conn).await;
+    tracing::debug!("查询没有开始时间的小时监控数据结果: {:?}", res.as_ref().map(|list| list.len()));
+    res
+}
+
+pub async fn update_start_time(
+    id: u32,
+    start_time: NaiveDateTime,
+    conn: &mut SqliteConnection,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_hour set ");
+    query_builder.push("start_time = ").push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" where id = ").push_bind(id);
+    let query = query_builder.build();
+    tracing::debug!("补全小时监控数据开始时间SQL: {}", query.sql());
+    query.execute(conn).await
+}
+
+pub async fn get_by_start_time(
+    network_name: &str,
+    start_time: NaiveDateTime,
+    conn: &mut SqliteConnection,
+) -> Result<Option<MonitorHour>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        format!("select {} from monitor_hour where ", ALL_FIELDS),
+    );
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" and start_time = ").push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    let query = query_builder.build_query_as::<MonitorHour>();
+    tracing::debug!("按开始时间查询小时监控数据SQL: {}", query.sql());
+    query.fetch_optional(conn).await
+}
+
+/// 把 source 小时的用量累加到 id 对应的小时上，并删除 source
+pub async fn merge_into(
+    id: u32,
+    source: &MonitorHour,
+    conn: &mut SqliteConnection,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_hour set ");
+    let mut separated = query_builder.separated(", ");
+    for (field, value) in [
+        ("uplink_traffic_usage", source.uplink_traffic_usage),
+        ("downlink_traffic_usage", source.downlink_traffic_usage),
+        ("uplink_packets_usage", source.uplink_packets_usage),
+        ("downlink_packets_usage", source.downlink_packets_usage),
+        ("uplink_errors_usage", source.uplink_errors_usage),
+        ("downlink_errors_usage", source.downlink_errors_usage),
+        ("uplink_drops_usage", source.uplink_drops_usage),
+        ("downlink_drops_usage", source.downlink_drops_usage),
+    ] {
+        separated.push(format!("{} = coalesce({}, 0) + ", field, field)).push_bind_unseparated(value.unwrap_or(0));
+    }
+    separated.push("is_estimated = max(coalesce(is_estimated, 0), ").push_bind_unseparated(source.is_estimated.unwrap_or(0)).push_unseparated(")");
+    query_builder.push(" where id = ").push_bind(id);
+    let query = query_builder.build();
+    tracing::debug!("合并小时监控数据SQL: {}", query.sql());
+    query.execute(&mut *conn).await?;
+
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("delete from monitor_hour where ");
+    query_builder.push("id = ").push_bind(source.id.unwrap());
+    let query = query_builder.build();
+    tracing::debug!("删除已合并的小时监控数据SQL: {}", query.sql());
+    query.execute(conn).await
+}
@@ -0,0 +1,120 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+
+const ALL_FIELDS: &str = "id, create_time, quota_name, cycle_start_date, cycle_end_date, percent, target, cost, day, condition";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct MonitorNotify {
+    pub id: Option<u32>,
+    pub create_time: Option<NaiveDateTime>,
+    pub quota_name: Option<String>,
+    pub cycle_start_date: Option<NaiveDate>,
+    pub cycle_end_date: Option<NaiveDate>,
+    pub percent: Option<u8>,
+    pub target: Option<String>,
+    pub cost: Option<String>,
+    pub day: Option<NaiveDate>,
+    pub condition: Option<String>,
+}
+
+pub async fn create(
+    entity: MonitorNotify,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_notify(");
+    let mut separated = query_builder.separated(", ");
+    if entity.quota_name.is_some() {
+        separated.push("quota_name");
+    }
+    if entity.cycle_start_date.is_some() {
+        separated.push("cycle_start_date");
+    }
+    if entity.cycle_end_date.is_some() {
+        separated.push("cycle_end_date");
+    }
+    if entity.percent.is_some() {
+        separated.push("percent");
+    }
+    if entity.target.is_some() {
+        separated.push("target");
+    }
+    if entity.cost.is_some() {
+        separated.push("cost");
+    }
+    if entity.day.is_some() {
+        separated.push("day");
+    }
+    if entity.condition.is_some() {
+        separated.push("condition");
+    }
+    query_builder.push(")  values(");
+    let mut separated = query_builder.separated(", ");
+    if let Some(quota_name) = entity.quota_name {
+        separated.push_bind(quota_name);
+    }
+    if let Some(cycle_start_date) = entity.cycle_start_date {
+        separated.push_bind(cycle_start_date);
+    }
+    if let Some(cycle_end_date) = entity.cycle_end_date {
+        separated.push_bind(cycle_end_date);
+    }
+    if let Some(percent) = entity.percent {
+        separated.push_bind(percent);
+    }
+    if let Some(target) = entity.target {
+        separated.push_bind(target);
+    }
+    if let Some(cost) = entity.cost {
+        separated.push_bind(cost);
+    }
+    if let Some(day) = entity.day {
+        separated.push_bind(day);
+    }
+    if let Some(condition) = entity.condition {
+        separated.push_bind(condition);
+    }
+    query_builder.push(")");
+
+    let query = query_builder.build();
+    tracing::debug!("插入阈值触发记录SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("插入阈值触发记录结果: {:?}", res);
+    res
+}
+
+pub async fn list_cycle_data(
+    quota_name: &str,
+    cycle_start_date: NaiveDate,
+    cycle_end_date: NaiveDate,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<MonitorNotify>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_notify where ", ALL_FIELDS));
+    query_builder.push("quota_name = ").push_bind(quota_name.to_string());
+    query_builder.push(" and cycle_start_date = ").push_bind(cycle_start_date);
+    query_builder.push(" and cycle_end_date = ").push_bind(cycle_end_date);
+    let query = query_builder.build_query_as::<MonitorNotify>();
+    tracing::debug!("查询周期阈值触发记录SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("查询周期阈值触发记录结果: {:?}", res);
+    res
+}
+
+/// 流量周期某个统计方向最近一次的触发记录，不限周期
+pub async fn get_last_data(
+    quota_name: &str,
+    target: &str,
+    pool: &Pool<Sqlite>,
+) -> Result<Option<MonitorNotify>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_notify where ", ALL_FIELDS));
+    query_builder.push("quota_name = ").push_bind(quota_name.to_string());
+    query_builder.push(" and target = ").push_bind(target.to_string());
+    query_builder.push(" order by id desc limit 1");
+    let query = query_builder.build_query_as::<MonitorNotify>();
+    tracing::debug!("查询最近一次阈值触发记录SQL: {}", query.sql());
+    let res = query.fetch_optional(pool).await;
+    tracing::debug!("查询最近一次阈值触发记录结果: {:?}", res);
+    res
+}
@@ -0,0 +1,153 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite, SqliteConnection};
+
+const ALL_FIELDS: &str = "id, create_time, network_name, reset_time, reason, pre_boot_id, boot_id, pre_ifindex, ifindex, pre_uplink_traffic_readings, uplink_traffic_readings, pre_downlink_traffic_readings, downlink_traffic_readings";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct MonitorReset {
+    pub id: Option<u32>,
+    pub create_time: Option<NaiveDateTime>,
+    pub network_name: Option<String>,
+    pub reset_time: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+    pub pre_boot_id: Option<String>,
+    pub boot_id: Option<String>,
+    pub pre_ifindex: Option<u32>,
+    pub ifindex: Option<u32>,
+    pub pre_uplink_traffic_readings: Option<i64>,
+    pub uplink_traffic_readings: Option<i64>,
+    pub pre_downlink_traffic_readings: Option<i64>,
+    pub downlink_traffic_readings: Option<i64>,
+}
+
+pub async fn create(
+    entity: MonitorReset,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_reset(");
+    let mut separated = query_builder.separated(", ");
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
+    if entity.reset_time.is_some() {
+        separated.push("reset_time");
+    }
+    if entity.reason.is_some() {
+        separated.push("reason");
+    }
+    if entity.pre_boot_id.is_some() {
+        separated.push("pre_boot_id");
+    }
+    if entity.boot_id.is_some() {
+        separated.push("boot_id");
+    }
+    if entity.pre_ifindex.is_some() {
+        separated.push("pre_ifindex");
+    }
+    if entity.ifindex.is_some() {
+        separated.push("ifindex");
+    }
+    if entity.pre_uplink_traffic_readings.is_some() {
+        separated.push("pre_uplink_traffic_readings");
+    }
+    if entity.uplink_traffic_readings.is_some() {
+        separated.push("uplink_traffic_readings");
+    }
+    if entity.pre_downlink_traffic_readings.is_some() {
+        separated.push("pre_downlink_traffic_readings");
+    }
+    if entity.downlink_traffic_readings.is_some() {
+        separated.push("downlink_traffic_readings");
+    }
+    query_builder.push(")  values(");
+    let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
+    if let Some(reset_time) = entity.reset_time {
+        separated.push_bind(reset_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    if let Some(reason) = entity.reason {
+        separated.push_bind(reason);
+    }
+    if let Some(pre_boot_id) = entity.pre_boot_id {
+        separated.push_bind(pre_boot_id);
+    }
+    if let Some(boot_id) = entity.boot_id {
+        separated.push_bind(boot_id);
+    }
+    if let Some(pre_ifindex) = entity.pre_ifindex {
+        separated.push_bind(pre_ifindex);
+    }
+    if let Some(ifindex) = entity.ifindex {
+        separated.push_bind(ifindex);
+    }
+    if let Some(pre_uplink_traffic_readings) = entity.pre_uplink_traffic_readings {
+        separated.push_bind(pre_uplink_traffic_readings);
+    }
+    if let Some(uplink_traffic_readings) = entity.uplink_traffic_readings {
+        separated.push_bind(uplink_traffic_readings);
+    }
+    if let Some(pre_downlink_traffic_readings) = entity.pre_downlink_traffic_readings {
+        separated.push_bind(pre_downlink_traffic_readings);
+    }
+    if let Some(downlink_traffic_readings) = entity.downlink_traffic_readings {
+        separated.push_bind(downlink_traffic_readings);
+    }
+    query_builder.push(")");
+
+    let query = query_builder.build();
+    tracing::debug!("插入计数器重置记录SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("插入计数器重置记录结果: {:?}", res);
+    res
+}
+
+pub async fn list_timerange_data(
+    network_name: Option<String>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<MonitorReset>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        format!("select {} from monitor_reset where ", ALL_FIELDS),
+    );
+    query_builder
+        .push("reset_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and reset_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    if let Some(network_name) = network_name {
+        query_builder.push(" and network_name = ").push_bind(network_name);
+    }
+    let query = query_builder.build_query_as::<MonitorReset>();
+    tracing::debug!("查询计数器重置记录SQL: {}", query.sql());
+    let res = query.fetch_all(pool).await;
+    tracing::debug!("查询计数器重置记录结果: {:?}", res);
+    res
+}
+
+pub async fn list_all(conn: &mut SqliteConnection) -> Result<Vec<MonitorReset>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_reset", ALL_FIELDS));
+    let query = query_builder.build_query_as::<MonitorReset>();
+    tracing::debug!("查询全部计数器重置记录SQL: {}", query.sql());
+    let res = query.fetch_all(conn).await;
+    tracing::debug!("查询全部计数器重置记录结果: {:?}", res.as_ref().map(|list| list.len()));
+    res
+}
+
+pub async fn update_reset_time(
+    id: u32,
+    reset_time: NaiveDateTime,
+    conn: &mut SqliteConnection,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_reset set ");
+    query_builder.push("reset_time = ").push_bind(reset_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" where id = ").push_bind(id);
+    let query = query_builder.build();
+    tracing::debug!("更新计数器重置记录时间SQL: {}", query.sql());
+    query.execute(conn).await
+}
@@ -1,13 +1,16 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::{Execute, Pool, QueryBuilder, Sqlite};
+use sqlx::{Execute, Pool, QueryBuilder, Sqlite, SqliteConnection};
 
-const ALL_FIELDS: &str = "id, create_time, start_time, end_time, uplink_traffic_readings, downlink_traffic_readings, uplink_traffic_usage, downlink_traffic_usage, time_interval, is_corrected";
+const ALL_FIELDS: &str = "id, create_time, network_name, start_time, end_time, uplink_traffic_readings, downlink_traffic_readings, uplink_traffic_usage, downlink_traffic_usage, time_interval, is_corrected, boot_id, ifindex, is_estimated, uplink_packets_readings, downlink_packets_readings, uplink_packets_usage, downlink_packets_usage, uplink_errors_readings, downlink_errors_readings, uplink_errors_usage, downlink_errors_usage, uplink_drops_readings, downlink_drops_readings, uplink_drops_usage, downlink_drops_usage";
+
+pub const PACKETS_USAGE_SUM_FIELDS: &str = "coalesce(sum(uplink_packets_usage), 0) as uplink_packets_usage, coalesce(sum(downlink_packets_usage), 0) as downlink_packets_usage, coalesce(sum(uplink_errors_usage), 0) as uplink_errors_usage, coalesce(sum(downlink_errors_usage), 0) as downlink_errors_usage, coalesce(sum(uplink_drops_usage), 0) as uplink_drops_usage, coalesce(sum(downlink_drops_usage), 0) as downlink_drops_usage";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
 pub struct MonitorSecond {
     pub id: Option<u32>,
     pub create_time: Option<NaiveDateTime>,
+    pub network_name: Option<String>,
     pub start_time: Option<NaiveDateTime>,
     pub end_time: Option<NaiveDateTime>,
     pub uplink_traffic_readings: Option<i64>,
@@ -16,6 +19,31 @@ pub struct MonitorSecond {
     pub downlink_traffic_usage: Option<i64>,
     pub time_interval: Option<i64>,
     pub is_corrected: Option<u32>,
+    pub boot_id: Option<String>,
+    pub ifindex: Option<u32>,
+    pub is_estimated: Option<u32>,
+    pub uplink_packets_readings: Option<i64>,
+    pub downlink_packets_readings: Option<i64>,
+    pub uplink_packets_usage: Option<i64>,
+    pub downlink_packets_usage: Option<i64>,
+    pub uplink_errors_readings: Option<i64>,
+    pub downlink_errors_readings: Option<i64>,
+    pub uplink_errors_usage: Option<i64>,
+    pub downlink_errors_usage: Option<i64>,
+    pub uplink_drops_readings: Option<i64>,
+    pub downlink_drops_readings: Option<i64>,
+    pub uplink_drops_usage: Option<i64>,
+    pub downlink_drops_usage: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct PacketsUsage {
+    pub uplink_packets_usage: i64,
+    pub downlink_packets_usage: i64,
+    pub uplink_errors_usage: i64,
+    pub downlink_errors_usage: i64,
+    pub uplink_drops_usage: i64,
+    pub downlink_drops_usage: i64,
 }
 
 pub async fn create(
@@ -24,6 +52,9 @@ pub async fn create(
 ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("insert into monitor_second(");
     let mut separated = query_builder.separated(", ");
+    if entity.network_name.is_some() {
+        separated.push("network_name");
+    }
     if entity.start_time.is_some() {
         separated.push("start_time");
     }
@@ -48,8 +79,56 @@ pub async fn create(
     if entity.is_corrected.is_some() {
         separated.push("is_corrected");
     }
+    if entity.boot_id.is_some() {
+        separated.push("boot_id");
+    }
+    if entity.ifindex.is_some() {
+        separated.push("ifindex");
+    }
+    if entity.is_estimated.is_some() {
+        separated.push("is_estimated");
+    }
+    if entity.uplink_packets_readings.is_some() {
+        separated.push("uplink_packets_readings");
+    }
+    if entity.downlink_packets_readings.is_some() {
+        separated.push("downlink_packets_readings");
+    }
+    if entity.uplink_packets_usage.is_some() {
+        separated.push("uplink_packets_usage");
+    }
+    if entity.downlink_packets_usage.is_some() {
+        separated.push("downlink_packets_usage");
+    }
+    if entity.uplink_errors_readings.is_some() {
+        separated.push("uplink_errors_readings");
+    }
+    if entity.downlink_errors_readings.is_some() {
+        separated.push("downlink_errors_readings");
+    }
+    if entity.uplink_errors_usage.is_some() {
+        separated.push("uplink_errors_usage");
+    }
+    if entity.downlink_errors_usage.is_some() {
+        separated.push("downlink_errors_usage");
+    }
+    if entity.uplink_drops_readings.is_some() {
+        separated.push("uplink_drops_readings");
+    }
+    if entity.downlink_drops_readings.is_some() {
+        separated.push("downlink_drops_readings");
+    }
+    if entity.uplink_drops_usage.is_some() {
+        separated.push("uplink_drops_usage");
+    }
+    if entity.downlink_drops_usage.is_some() {
+        separated.push("downlink_drops_usage");
+    }
     query_builder.push(")  values(");
     let mut separated = query_builder.separated(", ");
+    if let Some(network_name) = entity.network_name {
+        separated.push_bind(network_name);
+    }
     if entity.start_time.is_some() {
         separated.push_bind(
             entity
@@ -86,6 +165,51 @@ pub async fn create(
     if entity.is_corrected.is_some() {
         separated.push_bind(entity.is_corrected.unwrap());
     }
+    if let Some(boot_id) = entity.boot_id {
+        separated.push_bind(boot_id);
+    }
+    if let Some(ifindex) = entity.ifindex {
+        separated.push_bind(ifindex);
+    }
+    if let Some(is_estimated) = entity.is_estimated {
+        separated.push_bind(is_estimated);
+    }
+    if let Some(uplink_packets_readings) = entity.uplink_packets_readings {
+        separated.push_bind(uplink_packets_readings);
+    }
+    if let Some(downlink_packets_readings) = entity.downlink_packets_readings {
+        separated.push_bind(downlink_packets_readings);
+    }
+    if let Some(uplink_packets_usage) = entity.uplink_packets_usage {
+        separated.push_bind(uplink_packets_usage);
+    }
+    if let Some(downlink_packets_usage) = entity.downlink_packets_usage {
+        separated.push_bind(downlink_packets_usage);
+    }
+    if let Some(uplink_errors_readings) = entity.uplink_errors_readings {
+        separated.push_bind(uplink_errors_readings);
+    }
+    if let Some(downlink_errors_readings) = entity.downlink_errors_readings {
+        separated.push_bind(downlink_errors_readings);
+    }
+    if let Some(uplink_errors_usage) = entity.uplink_errors_usage {
+        separated.push_bind(uplink_errors_usage);
+    }
+    if let Some(downlink_errors_usage) = entity.downlink_errors_usage {
+        separated.push_bind(downlink_errors_usage);
+    }
+    if let Some(uplink_drops_readings) = entity.uplink_drops_readings {
+        separated.push_bind(uplink_drops_readings);
+    }
+    if let Some(downlink_drops_readings) = entity.downlink_drops_readings {
+        separated.push_bind(downlink_drops_readings);
+    }
+    if let Some(uplink_drops_usage) = entity.uplink_drops_usage {
+        separated.push_bind(uplink_drops_usage);
+    }
+    if let Some(downlink_drops_usage) = entity.downlink_drops_usage {
+        separated.push_bind(downlink_drops_usage);
+    }
     query_builder.push(")");
 
     let query = query_builder.build();
@@ -95,10 +219,15 @@ pub async fn create(
     res
 }
 
-pub async fn get_pre_data(pool: &Pool<Sqlite>) -> Result<Option<MonitorSecond>, sqlx::Error> {
+pub async fn get_pre_data(
+    network_name: &str,
+    pool: &Pool<Sqlite>,
+) -> Result<Option<MonitorSecond>, sqlx::Error> {
     let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
-        "select * from monitor_second where is_corrected = false order by end_time desc limit 1",
+        format!("select {} from monitor_second where is_corrected = false and ", ALL_FIELDS),
     );
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" order by end_time desc limit 1");
     let query = query_builder.build_query_as::<MonitorSecond>();
     tracing::debug!("查询上一节点秒级监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
@@ -107,6 +236,7 @@ pub async fn get_pre_data(pool: &Pool<Sqlite>) -> Result<Option<MonitorSecond>,
 }
 
 pub async fn sum_timerange_data(
+    network_name_list: &[String],
     start_time: NaiveDateTime,
     end_time: NaiveDateTime,
     pool: &Pool<Sqlite>,
@@ -120,6 +250,12 @@ pub async fn sum_timerange_data(
     query_builder
         .push(" and start_time < ")
         .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" and network_name in (");
+    let mut separated = query_builder.separated(", ");
+    for network_name in network_name_list {
+        separated.push_bind(network_name.clone());
+    }
+    separated.push_unseparated(")");
     let query = query_builder.build_query_as::<(i64, i64)>();
     tracing::debug!("查询区域秒级监控数据SQL: {}", query.sql());
     let res = query.fetch_optional(pool).await;
@@ -127,7 +263,55 @@ pub async fn sum_timerange_data(
     res
 }
 
+pub async fn sum_timerange_packets_data(
+    network_name: &str,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<PacketsUsage, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+        "select {} from monitor_second where ",
+        PACKETS_USAGE_SUM_FIELDS
+    ));
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder
+        .push(" and start_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and start_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    let query = query_builder.build_query_as::<PacketsUsage>();
+    tracing::debug!("查询区域秒级包数据SQL: {}", query.sql());
+    let res = query.fetch_one(pool).await;
+    tracing::debug!("查询区域秒级包数据结果: {:?}", res);
+    res
+}
+
+pub async fn exists_estimated_data(
+    network_name: &str,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    pool: &Pool<Sqlite>,
+) -> Result<bool, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "select count(1) from monitor_second where is_estimated = 1 and ",
+    );
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder
+        .push(" and start_time >= ")
+        .push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder
+        .push(" and start_time < ")
+        .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    let query = query_builder.build_query_scalar::<i64>();
+    tracing::debug!("查询区域秒级估算数据SQL: {}", query.sql());
+    let res = query.fetch_one(pool).await;
+    tracing::debug!("查询区域秒级估算数据结果: {:?}", res);
+    res.map(|count| count > 0)
+}
+
 pub async fn list_timerange_data(
+    network_name: Option<String>,
     start_time: NaiveDateTime,
     end_time: NaiveDateTime,
     pool: &Pool<Sqlite>,
@@ -141,6 +325,9 @@ pub async fn list_timerange_data(
     query_builder
         .push(" and start_time < ")
         .push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    if let Some(network_name) = network_name {
+        query_builder.push(" and network_name = ").push_bind(network_name);
+    }
     let query = query_builder.build_query_as::<MonitorSecond>();
     tracing::debug!("查询区域秒级监控数据SQL: {}", query.sql());
     let res = query.fetch_all(pool).await;
@@ -162,3 +349,42 @@ pub async fn delete_by_date(
     tracing::debug!("删除秒级监控数据结果: {:?}", res);
     res
 }
+
+pub async fn fill_network_name(
+    network_name: &str,
+    pool: &Pool<Sqlite>,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_second set ");
+    query_builder.push("network_name = ").push_bind(network_name.to_string());
+    query_builder.push(" where network_name = ''");
+    let query = query_builder.build();
+    tracing::debug!("补全秒级监控数据网卡名称SQL: {}", query.sql());
+    let res = query.execute(pool).await;
+    tracing::debug!("补全秒级监控数据网卡名称结果: {:?}", res);
+    res
+}
+
+pub async fn list_all(conn: &mut SqliteConnection) -> Result<Vec<MonitorSecond>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("select {} from monitor_second", ALL_FIELDS));
+    let query = query_builder.build_query_as::<MonitorSecond>();
+    tracing::debug!("查询全部秒级监控数据SQL: {}", query.sql());
+    let res = query.fetch_all(conn).await;
+    tracing::debug!("查询全部秒级监控数据结果: {:?}", res.as_ref().map(|list| list.len()));
+    res
+}
+
+pub async fn update_time(
+    id: u32,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    conn: &mut SqliteConnection,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("update monitor_second set ");
+    query_builder.push("start_time = ").push_bind(start_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(", end_time = ").push_bind(end_time.format("%Y-%m-%dT%H:%M:%S").to_string());
+    query_builder.push(" where id = ").push_bind(id);
+    let query = query_builder.build();
+    tracing::debug!("更新秒级监控数据时间SQL: {}", query.sql());
+    query.execute(conn).await
+}
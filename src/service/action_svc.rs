@@ -0,0 +1,392 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{
+    config::{
+        app_config::{ActionConfig, ShellActionConfig, SignalActionConfig, SystemdUnitActionConfig, TypedActionConfig},
+        state::AppState,
+    },
+    mapper::monitor_event_mapper::{self, MonitorEvent},
+    util::{command_util, http_util, time_util},
+};
+
+/// 重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// signal systemd-unit 命令的超时时间
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// 执行记录中保存的输出最大字符数
+const OUTPUT_MAX_CHARS: usize = 2000;
+
+const SIGNAL_LIST: [&str; 10] = ["HUP", "INT", "QUIT", "KILL", "USR1", "USR2", "TERM", "CONT", "STOP", "TSTP"];
+const UNIT_OPERATION_LIST: [&str; 4] = ["start", "stop", "restart", "reload"];
+
+/// 动作的触发原因和用量，shell 中为 TM_ 开头的环境变量，http 的 url 和 body 中为 {{变量名}}
+#[derive(Clone, Debug, Default)]
+pub struct ActionContext {
+    /// notify pacing pacing_end cycle_start cycle_end
+    pub event: String,
+    pub quota_name: String,
+    pub cycle_start_date: NaiveDate,
+    pub cycle_end_date: NaiveDate,
+    pub target: Option<String>,
+    pub condition: Option<String>,
+    /// 统计方向的用量，流量为字节，95 计费带宽为 bit/s，超额费用为金额
+    pub usage: Option<Decimal>,
+    pub limit: Option<Decimal>,
+    pub message: Option<String>,
+}
+
+impl ActionContext {
+    fn variables(&self, vps_name: &str) -> Vec<(&'static str, String)> {
+        let percent = match (self.usage, self.limit) {
+            (Some(usage), Some(limit)) if limit > Decimal::ZERO => {
+                (usage / limit * Decimal::ONE_HUNDRED).round_dp(2).normalize().to_string()
+            }
+            _ => String::new(),
+        };
+        vec![
+            ("event", self.event.clone()),
+            ("vps_name", vps_name.to_string()),
+            ("quota_name", self.quota_name.clone()),
+            ("cycle_start_date", self.cycle_start_date.to_string()),
+            ("cycle_end_date", self.cycle_end_date.to_string()),
+            ("target", self.target.clone().unwrap_or_default()),
+            ("condition", self.condition.clone().unwrap_or_default()),
+            ("usage", self.usage.map(|ele| ele.normalize().to_string()).unwrap_or_default()),
+            ("limit", self.limit.map(|ele| ele.normalize().to_string()).unwrap_or_default()),
+            ("percent", percent),
+            ("message", self.message.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+/// 模板变量的转义方式，url 中按百分号编码，JSON body 中转义为 JSON 字符串的内容
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Escape {
+    Raw,
+    Url,
+    Json,
+}
+
+fn escape(value: &str, escape: Escape) -> String {
+    match escape {
+        Escape::Raw => value.to_string(),
+        Escape::Url => value
+            .bytes()
+            .map(|ele| match ele {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (ele as char).to_string(),
+                _ => format!("%{:02X}", ele),
+            })
+            .collect(),
+        Escape::Json => {
+            let res = serde_json::Value::String(value.to_string()).to_string();
+            res[1..res.len() - 1].to_string()
+        }
+    }
+}
+
+/// 替换模板中的 {{变量名}}，变量值按 escape 转义，没有的变量保持原样
+fn render(template: &str, variables: &[(&str, String)], escape_type: Escape) -> String {
+    variables.iter().fold(template.to_string(), |res, (key, value)| {
+        res.replace(&format!("{{{{{}}}}}", key), &escape(value, escape_type))
+    })
+}
+
+/// 默认 Content-Type 为 application/json，body 中的变量按 JSON 转义，配置了其他 Content-Type 时不转义
+fn body_escape(headers: &[(String, String)]) -> Escape {
+    match headers.iter().find(|(key, _)| key.eq_ignore_ascii_case("Content-Type")) {
+        Some((_, value)) if !value.to_lowercase().contains("json") => Escape::Raw,
+        _ => Escape::Json,
+    }
+}
+
+/// 字符串动作为不限制执行时间(timeout_secs 为 0)、不重试的 shell 命令
+fn typed_action(action: &ActionConfig) -> TypedActionConfig {
+    match action {
+        ActionConfig::Command(command) => TypedActionConfig::Shell(ShellActionConfig {
+            command: command.clone(),
+            timeout_secs: 0,
+            retries: 0,
+            env: None,
+        }),
+        ActionConfig::Typed(action) => action.clone(),
+    }
+}
+
+fn signal_name(signal: &str) -> String {
+    let signal = signal.to_uppercase();
+    signal.strip_prefix("SIG").map(str::to_string).unwrap_or(signal)
+}
+
+/// 生成周期时校验动作配置，name 为配置项名称
+pub fn validate(actions: &[ActionConfig], name: &str) -> anyhow::Result<()> {
+    for action in actions {
+        match typed_action(action) {
+            TypedActionConfig::Shell(shell) if shell.command.trim().is_empty() => {
+                return Err(anyhow!("config[liftcycle][{}][command] 不能为空", name));
+            }
+            TypedActionConfig::Shell(_) => {}
+            TypedActionConfig::Http(http) => {
+                if !http.url.starts_with("http://") && !http.url.starts_with("https://") {
+                    return Err(anyhow!("config[liftcycle][{}][url] 配置填写错误，需要以 http:// 或 https:// 开头", name));
+                }
+                if reqwest::Method::from_bytes(http.method.to_uppercase().as_bytes()).is_err() {
+                    return Err(anyhow!("config[liftcycle][{}][method] 配置填写错误，没有这样的请求方法", name));
+                }
+            }
+            TypedActionConfig::Signal(signal) => {
+                if !SIGNAL_LIST.contains(&signal_name(&signal.signal).as_str()) {
+                    return Err(anyhow!(
+                        "config[liftcycle][{}][signal] 配置填写错误，只能填写 {}",
+                        name,
+                        SIGNAL_LIST.join(" ")
+                    ));
+                }
+                let count = [signal.pid.is_some(), signal.pid_file.is_some(), signal.process_name.is_some()]
+                    .iter()
+                    .filter(|ele| **ele)
+                    .count();
+                if count != 1 {
+                    return Err(anyhow!("config[liftcycle][{}] pid pid_file process_name 需要配置其中一个", name));
+                }
+                if signal.process_name.as_ref().is_some_and(|ele| !valid_name(ele)) {
+                    return Err(anyhow!("config[liftcycle][{}][process_name] 配置填写错误，包含不支持的字符", name));
+                }
+            }
+            TypedActionConfig::SystemdUnit(unit) => {
+                if unit.unit.is_empty() || !valid_name(&unit.unit) {
+                    return Err(anyhow!("config[liftcycle][{}][unit] 配置填写错误，包含不支持的字符", name));
+                }
+                if !UNIT_OPERATION_LIST.contains(&unit.operation.as_str()) {
+                    return Err(anyhow!(
+                        "config[liftcycle][{}][operation] 配置填写错误，只能填写 {}",
+                        name,
+                        UNIT_OPERATION_LIST.join(" ")
+                    ));
+                }
+            }
+        }
+    }
+    anyhow::Ok(())
+}
+
+/// 进程名和服务名会拼接到命令中，只允许字母数字和 @ . _ - :
+fn valid_name(name: &str) -> bool {
+    name.chars().all(|ele| ele.is_ascii_alphanumeric() || "@._-:".contains(ele))
+}
+
+/// 在一个后台任务中按顺序依次执行各触发原因的动作，不阻塞流量统计
+pub fn spawn(app_state: &AppState, action_list: Vec<(ActionContext, Vec<ActionConfig>)>) {
+    let action_list: Vec<_> = action_list.into_iter().filter(|(_, actions)| !actions.is_empty()).collect();
+    if action_list.is_empty() {
+        return;
+    }
+    let app_state = app_state.clone();
+    tokio::spawn(async move {
+        for (context, actions) in action_list {
+            execute(&app_state, &context, &actions).await;
+        }
+    });
+}
+
+/// 依次执行动作，失败时按配置重试，每个动作保存一条执行记录
+pub async fn execute(app_state: &AppState, context: &ActionContext, actions: &[ActionConfig]) {
+    let variables = context.variables(&app_state.config.vps_name);
+    for action in actions {
+        let action = typed_action(action);
+        let (action_type, action_text, retries) = match &action {
+            TypedActionConfig::Shell(shell) => ("shell", shell.command.clone(), shell.retries),
+            TypedActionConfig::Http(http) => ("http", format!("{} {}", http.method.to_uppercase(), render(&http.url, &variables, Escape::Url)), http.retries),
+            TypedActionConfig::Signal(signal) => ("signal", signal_command(signal), 0),
+            TypedActionConfig::SystemdUnit(unit) => ("systemd-unit", systemd_unit_command(unit), 0),
+        };
+        tracing::info!("流量周期 {} {} 执行 {} 动作: {}", context.quota_name, context.event, action_type, action_text);
+        let start_time = time_util::now();
+        let start = std::time::Instant::now();
+        let mut attempts = 0;
+        let res = loop {
+            attempts += 1;
+            let res = execute_once(&action, &variables).await;
+            match res {
+                Err(e) if attempts <= retries => {
+                    tracing::warn!("执行 {} 动作失败，{} 秒后第 {} 次重试: {:#}", action_type, RETRY_INTERVAL.as_secs(), attempts, e);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+                res => break res,
+            }
+        };
+        let (success, output) = match res {
+            Ok(output) => {
+                tracing::info!("执行 {} 动作成功，执行结果: {}", action_type, output);
+                (true, output)
+            }
+            Err(e) => {
+                tracing::error!("执行 {} 动作失败，执行结果: {:#}", action_type, e);
+                (false, format!("{:#}", e))
+            }
+        };
+        let monitor_event = MonitorEvent {
+            id: None,
+            create_time: None,
+            quota_name: Some(context.quota_name.clone()),
+            event: Some(context.event.clone()),
+            target: context.target.clone(),
+            condition: context.condition.clone(),
+            action_type: Some(action_type.to_string()),
+            action: Some(action_text),
+            success: Some(success as u32),
+            attempts: Some(attempts),
+            output: Some(output.chars().take(OUTPUT_MAX_CHARS).collect()),
+            start_time: Some(start_time),
+            duration_ms: Some(start.elapsed().as_millis() as i64),
+        };
+        if let Err(e) = monitor_event_mapper::create(monitor_event, &app_state.db_pool).await {
+            tracing::error!("保存动作执行记录失败: {:?}", e);
+        }
+    }
+}
+
+async fn execute_once(action: &TypedActionConfig, variables: &[(&str, String)]) -> anyhow::Result<String> {
+    match action {
+        TypedActionConfig::Shell(shell) => {
+            let mut envs: Vec<(String, String)> = variables
+                .iter()
+                .map(|(key, value)| (format!("TM_{}", key.to_uppercase()), value.clone()))
+                .collect();
+            if let Some(env) = &shell.env {
+                envs.extend(env.iter().map(|(key, value)| (key.clone(), render(value, variables, Escape::Raw))));
+            }
+            let timeout = (shell.timeout_secs > 0).then(|| Duration::from_secs(shell.timeout_secs));
+            execute_command(&shell.command, &envs, timeout).await
+        }
+        TypedActionConfig::Http(http) => {
+            let headers: Vec<(String, String)> = http
+                .headers
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key.clone(), render(value, variables, Escape::Raw)))
+                .collect();
+            let body_escape = body_escape(&headers);
+            http_util::request(
+                &http.method,
+                &render(&http.url, variables, Escape::Url),
+                &headers,
+                http.body.as_ref().map(|body| render(body, variables, body_escape)),
+                Duration::from_secs(http.timeout_secs),
+            )
+            .await
+        }
+        TypedActionConfig::Signal(signal) => {
+            let command = match &signal.pid_file {
+                Some(pid_file) => {
+                    let pid = std::fs::read_to_string(pid_file)
+                        .map_err(|e| anyhow!("读取 pid 文件 {} 失败: {}", pid_file, e))?;
+                    let pid: u32 = pid.trim().parse().map_err(|_| anyhow!("pid 文件 {} 内容错误: {}", pid_file, pid.trim()))?;
+                    format!("kill -s {} {}", signal_name(&signal.signal), pid)
+                }
+                None => signal_command(signal),
+            };
+            execute_command(&command, &[], Some(COMMAND_TIMEOUT)).await
+        }
+        TypedActionConfig::SystemdUnit(unit) => execute_command(&systemd_unit_command(unit), &[], Some(COMMAND_TIMEOUT)).await,
+    }
+}
+
+async fn execute_command(command: &str, envs: &[(String, String)], timeout: Option<Duration>) -> anyhow::Result<String> {
+    let res = command_util::execute_to_output_with_env(".".to_string(), vec![command.to_string()], envs, timeout).await?;
+    if res.status.success() {
+        anyhow::Ok(String::from_utf8_lossy(&res.stdout).to_string())
+    } else {
+        Err(anyhow!("{} {}", res.status, String::from_utf8_lossy(&res.stderr)))
+    }
+}
+
+/// pid_file 在执行时读取，这里只用于显示
+fn signal_command(signal: &SignalActionConfig) -> String {
+    let signal_name = signal_name(&signal.signal);
+    match (&signal.pid, &signal.pid_file, &signal.process_name) {
+        (Some(pid), _, _) => format!("kill -s {} {}", signal_name, pid),
+        (_, Some(pid_file), _) => format!("kill -s {} $(cat {})", signal_name, pid_file),
+        (_, _, Some(process_name)) => format!("pkill -{} -x {}", signal_name, process_name),
+        _ => String::new(),
+    }
+}
+
+/// 在 docker 中时通过 nsenter 进入宿主机的命名空间执行 systemctl，需要 --pid=host 和 --privileged
+fn systemd_unit_command(unit: &SystemdUnitActionConfig) -> String {
+    let command = format!("systemctl {} {}", unit.operation, unit.unit);
+    if unit.nsenter {
+        format!("nsenter -a -t 1 {}", command)
+    } else {
+        command
+    }
+}
+
+#[cfg(test)]
+mod action_svc_test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let context = ActionContext {
+            event: "notify".to_string(),
+            quota_name: "main".to_string(),
+            target: Some("total".to_string()),
+            usage: Some(dec!(800)),
+            limit: Some(dec!(1000)),
+            ..Default::default()
+        };
+        let variables = context.variables("vps");
+        assert_eq!(
+            render(r#"{"text": "{{vps_name}} {{quota_name}} {{percent}}% {{usage}}/{{limit}} {{unknown}}"}"#, &variables, Escape::Json),
+            r#"{"text": "vps main 80% 800/1000 {{unknown}}"}"#
+        );
+
+        // 变量中的引号、反斜杠、换行在 JSON body 中转义，在 url 中按百分号编码
+        let context = ActionContext { message: Some("超80%\n".to_string()), ..context };
+        let variables = context.variables("my \"vps\"\\");
+        let body = render(r#"{"text": "{{vps_name}} {{message}}"}"#, &variables, Escape::Json);
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["text"], "my \"vps\"\\ 超80%\n");
+        assert_eq!(
+            render("https://example.com/hook?vps={{vps_name}}&p={{percent}}", &variables, Escape::Url),
+            "https://example.com/hook?vps=my%20%22vps%22%5C&p=80"
+        );
+        assert_eq!(body_escape(&[]), Escape::Json);
+        assert_eq!(body_escape(&[("content-type".to_string(), "text/plain".to_string())]), Escape::Raw);
+    }
+
+    #[test]
+    fn validate_test() {
+        let actions: Vec<ActionConfig> = serde_json::from_str(
+            r#"["echo 1", {"type": "shell", "command": "echo 2", "retries": 2},
+                {"type": "http", "url": "https://example.com/hook", "body": "{{message}}"},
+                {"type": "signal", "signal": "SIGHUP", "process_name": "nginx"},
+                {"type": "systemd-unit", "unit": "xray.service", "operation": "stop"}]"#,
+        )
+        .unwrap();
+        assert!(validate(&actions, "notify][exec").is_ok());
+        assert!(matches!(&actions[0], ActionConfig::Command(command) if command == "echo 1"));
+        // 字符串形式不限制执行时间，对象形式默认 60 秒
+        assert!(matches!(typed_action(&actions[0]), TypedActionConfig::Shell(shell) if shell.timeout_secs == 0));
+        assert!(matches!(typed_action(&actions[1]), TypedActionConfig::Shell(shell) if shell.timeout_secs == 60));
+        assert!(matches!(&actions[2], ActionConfig::Typed(TypedActionConfig::Http(http)) if http.method == "POST"));
+
+        let invalid_list = [
+            r#"{"type": "signal", "signal": "TERM"}"#,
+            r#"{"type": "signal", "signal": "BOOM", "pid": 1}"#,
+            r#"{"type": "signal", "process_name": "a; rm -rf /"}"#,
+            r#"{"type": "systemd-unit", "unit": "xray", "operation": "mask"}"#,
+            r#"{"type": "http", "url": "example.com"}"#,
+        ];
+        for invalid in invalid_list {
+            let action: ActionConfig = serde_json::from_str(invalid).unwrap();
+            assert!(validate(&[action], "notify][exec").is_err(), "{}", invalid);
+        }
+    }
+}
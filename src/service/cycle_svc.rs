@@ -0,0 +1,624 @@
+use anyhow::anyhow;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+
+use crate::{
+    config::app_config::{PricingConfig, TrafficCycleConfig},
+    config::state::{AppState, CycleAppState, CycleBillingMode, CycleForecast, CyclePricing, CyclePricingTier, FreeWindow},
+    mapper::{
+        monitor_cycle_mapper::{self, MonitorCycle},
+        monitor_day_mapper::{self, MonitorDay},
+        monitor_five_minute_mapper,
+        monitor_hour_mapper,
+        monitor_notify_mapper::{self, MonitorNotify},
+    },
+    service::action_svc::{self, ActionContext},
+    util::{tg_util, time_util, traffic_util::{parse_traffic_limit, traffic_show}},
+};
+
+/// 计算从上一周期结转到 cycle_start_date 开始的周期的流量，返回 (计入流量, 上行, 下行)，没有配置结转或对应限制时为空
+/// cycle 中的限制需为配置的限制，上一周期没有历史时不结转
+pub async fn cycle_rollover(
+    app_state: &AppState,
+    liftcycle: &TrafficCycleConfig,
+    cycle: &CycleAppState,
+    cycle_start_date: NaiveDate,
+) -> anyhow::Result<(Option<i64>, Option<i64>, Option<i64>)> {
+    let rollover = match &liftcycle.rollover {
+        Some(rollover) => rollover,
+        None => return anyhow::Ok((None, None, None)),
+    };
+    if rollover.percent > 100 {
+        return Err(anyhow!("config[liftcycle][rollover][percent] 配置填写错误，只能填写 0 ~ 100"));
+    }
+    let max = parse_traffic_limit(&rollover.max, "rollover][max")?;
+    let pre_cycle = match time_util::previous_cycle_start_date(&cycle.cycle_type, cycle_start_date) {
+        Some(pre_start) => {
+            let pre_end = cycle_start_date - Duration::days(1);
+            monitor_cycle_mapper::get_cycle_data(&cycle.name, pre_start, pre_end, &app_state.db_pool).await?
+        }
+        None => None,
+    };
+    let carry = |limit: Option<i64>, pre_remain: Option<(i64, i64)>| {
+        limit.map(|_| rollover_amount(pre_remain.map(|(limit, usage)| limit - usage).unwrap_or(0), rollover.percent, max))
+    };
+    let pre_cycle = pre_cycle.as_ref();
+    anyhow::Ok((
+        carry(cycle.traffic_limit, pre_cycle.and_then(|ele| ele.traffic_limit.zip(ele.traffic_usage))),
+        carry(cycle.uplink_limit, pre_cycle.and_then(|ele| ele.uplink_limit.zip(ele.uplink_traffic_usage))),
+        carry(cycle.downlink_limit, pre_cycle.and_then(|ele| ele.downlink_limit.zip(ele.downlink_traffic_usage))),
+    ))
+}
+
+/// 剩余流量按百分比结转，不超过 max，超额时没有结转
+fn rollover_amount(remain: i64, percent: u8, max: Option<i64>) -> i64 {
+    let amount = std::cmp::max(remain, 0) * percent as i64 / 100;
+    match max {
+        Some(max) => std::cmp::min(amount, max),
+        None => amount,
+    }
+}
+
+/// 计算 start_date 开始到 end_time 之间 5 分钟采样的 95 计费带宽，单位 bit/s
+/// 每个采样按统计方法合并各网卡上下行后换算为速率，免费时段的采样不参与计算
+pub async fn cycle_rate_p95(
+    app_state: &AppState,
+    cycle: &CycleAppState,
+    start_date: NaiveDate,
+    end_time: NaiveDateTime,
+) -> anyhow::Result<i64> {
+    let tz = app_state.config.tz();
+    let five_minute_data_list = monitor_five_minute_mapper::sum_timerange_group_by_time(
+        &cycle.network_name,
+        time_util::cycle_day_start(start_date, cycle.reset_hour, tz),
+        end_time,
+        &app_state.db_pool,
+    )
+    .await?;
+    let usage_list = five_minute_data_list
+        .iter()
+        .filter(|(start_time, _, _, _)| !cycle.is_free_time(*start_time, tz))
+        .map(|(_, uplink, downlink, is_estimated)| (cycle.statistic_method.traffic_usage(*uplink, *downlink), *is_estimated))
+        .collect::<Vec<(i64, bool)>>();
+    anyhow::Ok(five_minute_rate_p95(&usage_list))
+}
+
+/// 按每 5 分钟的用量计算 95 计费带宽，应用停止期间的估算数据不是真实速率，不参与计算
+fn five_minute_rate_p95(usage_list: &[(i64, bool)]) -> i64 {
+    let mut rate_list = usage_list
+        .iter()
+        .filter(|(_, is_estimated)| !is_estimated)
+        .map(|(usage, _)| usage * 8 / 300)
+        .collect::<Vec<i64>>();
+    percentile_95(&mut rate_list)
+}
+
+/// 去掉最高的 5% 后取最大值，没有采样时为 0
+fn percentile_95(rate_list: &mut [i64]) -> i64 {
+    if rate_list.is_empty() {
+        return 0;
+    }
+    rate_list.sort_unstable();
+    rate_list[(rate_list.len() * 95).div_ceil(100) - 1]
+}
+
+/// 解析超额计费，没有配置 included 时以 traffic_limit 为免费额度，只填 price_per_gb 时为单一价格
+pub fn parse_pricing(
+    pricing: &Option<PricingConfig>,
+    billing_mode: &CycleBillingMode,
+    traffic_limit: Option<i64>,
+) -> anyhow::Result<Option<CyclePricing>> {
+    let pricing = match pricing {
+        Some(pricing) => pricing,
+        None => return anyhow::Ok(None),
+    };
+    if *billing_mode != CycleBillingMode::Volume {
+        return Err(anyhow!("config[liftcycle][pricing] 只支持 billing_mode 为 volume"));
+    }
+    let included = match parse_traffic_limit(&pricing.included, "pricing][included")?.or(traffic_limit) {
+        Some(included) => included,
+        None => return Err(anyhow!("config[liftcycle][pricing][included] 没有配置 traffic_limit 时必须配置")),
+    };
+    let tiers = match (&pricing.tiers, pricing.price_per_gb) {
+        (Some(tiers), None) if !tiers.is_empty() => {
+            let mut tier_list = vec![];
+            for tier in tiers {
+                let up_to = parse_traffic_limit(&tier.up_to, "pricing][tiers][up_to")?;
+                let lower = tier_list.last().and_then(|ele: &CyclePricingTier| ele.up_to).unwrap_or(0);
+                if up_to.is_some_and(|up_to| up_to <= lower) {
+                    return Err(anyhow!("config[liftcycle][pricing][tiers][up_to] 需要按升序填写"));
+                }
+                if tier_list.last().is_some_and(|ele: &CyclePricingTier| ele.up_to.is_none()) {
+                    return Err(anyhow!("config[liftcycle][pricing][tiers] 只有最后一档可以不填 up_to"));
+                }
+                tier_list.push(CyclePricingTier { up_to, price_per_gb: tier.price_per_gb });
+            }
+            tier_list
+        }
+        (None, Some(price_per_gb)) => vec![CyclePricingTier { up_to: None, price_per_gb }],
+        _ => return Err(anyhow!("config[liftcycle][pricing] price_per_gb 和 tiers 需要配置其中一个")),
+    };
+    if tiers.iter().any(|ele| ele.price_per_gb < Decimal::ZERO) {
+        return Err(anyhow!("config[liftcycle][pricing][price_per_gb] 不能小于 0"));
+    }
+    anyhow::Ok(Some(CyclePricing { currency: pricing.currency.clone(), included, tiers }))
+}
+
+/// 当天计入流量超过每日额度的倍数时通知并执行 exec，每个周期日只触发一次，进入下一个周期日后执行 end_exec
+pub async fn verify_cycle_pacing(app_state: &AppState, cycle: &mut CycleAppState, quota_title: &str) {
+    let config = &app_state.config;
+    let pacing = match &mut cycle.pacing {
+        Some(pacing) => pacing,
+        None => return,
+    };
+    // 每日额度依赖当天的用量预测基准，生成周期时已校验 traffic_limit
+    let forecast = match &cycle.forecast {
+        Some(forecast) => forecast,
+        None => return,
+    };
+    // 先保存记录再执行动作，记录保存失败时不执行，下次采集时重试
+    if pacing.triggered_day.is_some_and(|ele| ele < forecast.day) {
+        let cycle_range = (cycle.current_cycle_start_date, cycle.current_cycle_end_date);
+        if let Err(e) = save_pacing_notify(app_state, &cycle.name, cycle_range, "pacing_end", forecast.day).await {
+            tracing::error!("保存每日额度结束记录失败，下次采集时重试: {:?}", e);
+            return;
+        }
+        pacing.triggered_day = None;
+        tracing::info!("流量周期 {} 进入新的一天，撤销每日额度的限制", cycle.name);
+        let context = ActionContext {
+            event: "pacing_end".to_string(),
+            quota_name: cycle.name.clone(),
+            cycle_start_date: cycle.current_cycle_start_date,
+            cycle_end_date: cycle.current_cycle_end_date,
+            ..Default::default()
+        };
+        action_svc::spawn(app_state, vec![(context, pacing.end_exec.clone())]);
+    }
+    let allowance = match daily_allowance(cycle.traffic_limit, forecast, cycle.current_cycle_end_date) {
+        Some(allowance) => allowance,
+        None => return,
+    };
+    pacing.allowance = Some(allowance);
+    let today_traffic_usage =
+        forecast.today_traffic_usage(&cycle.statistic_method, cycle.uplink_traffic_usage, cycle.downlink_traffic_usage);
+    if pacing.triggered_day.is_some()
+        || Decimal::from_i64(today_traffic_usage).unwrap() <= Decimal::from_i64(allowance).unwrap() * pacing.multiple
+    {
+        return;
+    }
+    let cycle_range = (cycle.current_cycle_start_date, cycle.current_cycle_end_date);
+    if let Err(e) = save_pacing_notify(app_state, &cycle.name, cycle_range, "pacing", forecast.day).await {
+        tracing::error!("保存每日额度触发记录失败，下次采集时重试: {:?}", e);
+        return;
+    }
+    pacing.triggered_day = Some(forecast.day);
+    tracing::warn!("{} {}今日流量使用超每日额度{}倍", config.vps_name, quota_title, pacing.multiple);
+    let text = format!(
+        "{} {}今日流量使用超每日额度{}倍 {}/{}",
+        config.vps_name,
+        quota_title,
+        pacing.multiple,
+        traffic_show(today_traffic_usage),
+        traffic_show(allowance)
+    );
+    tg_util::send_msg(config, text.clone()).await;
+    let context = ActionContext {
+        event: "pacing".to_string(),
+        quota_name: cycle.name.clone(),
+        cycle_start_date: cycle.current_cycle_start_date,
+        cycle_end_date: cycle.current_cycle_end_date,
+        usage: Some(Decimal::from(today_traffic_usage)),
+        limit: Some(Decimal::from(allowance)),
+        message: Some(text),
+        ..Default::default()
+    };
+    action_svc::spawn(app_state, vec![(context, pacing.exec.clone())]);
+}
+
+/// 每日额度，周期剩余流量按包含今天的剩余天数平均分配，超出限制后为 0
+pub fn daily_allowance(traffic_limit: Option<i64>, forecast: &CycleForecast, current_cycle_end_date: NaiveDate) -> Option<i64> {
+    let remain_day = (current_cycle_end_date - forecast.day).num_days() + 1;
+    traffic_limit.map(|traffic_limit| std::cmp::max(traffic_limit - forecast.history_traffic_usage, 0) / remain_day)
+}
+
+async fn save_pacing_notify(
+    app_state: &AppState,
+    quota_name: &str,
+    (cycle_start_date, cycle_end_date): (NaiveDate, NaiveDate),
+    target: &str,
+    day: NaiveDate,
+) -> anyhow::Result<()> {
+    let monitor_notify = MonitorNotify {
+        id: None,
+        create_time: None,
+        quota_name: Some(quota_name.to_string()),
+        cycle_start_date: Some(cycle_start_date),
+        cycle_end_date: Some(cycle_end_date),
+        percent: Some(0),
+        target: Some(target.to_string()),
+        cost: None,
+        day: Some(day),
+        condition: None,
+    };
+    monitor_notify_mapper::create(monitor_notify, &app_state.db_pool).await?;
+    anyhow::Ok(())
+}
+
+/// 最近一次超过每日额度后还没有执行 end_exec 时返回触发的周期日，跨周期时同样有效
+pub async fn pacing_triggered_day(app_state: &AppState, quota_name: &str) -> anyhow::Result<Option<NaiveDate>> {
+    let triggered = monitor_notify_mapper::get_last_data(quota_name, "pacing", &app_state.db_pool).await?;
+    let ended = monitor_notify_mapper::get_last_data(quota_name, "pacing_end", &app_state.db_pool).await?;
+    anyhow::Ok(match (triggered, ended) {
+        (Some(triggered), Some(ended)) if ended.id >= triggered.id => None,
+        (Some(triggered), _) => triggered.day,
+        (None, _) => None,
+    })
+}
+
+/// 每个周期日重新计算一次用量预测的基准，并按当前计入流量更新预计周期用量，95 计费带宽计费时不预测
+pub async fn update_forecast(app_state: &AppState, cycle: &mut CycleAppState) -> anyhow::Result<()> {
+    if cycle.billing_mode == CycleBillingMode::Percentile95 {
+        return anyhow::Ok(());
+    }
+    let today = time_util::cycle_today(cycle.reset_hour, app_state.config.tz());
+    if cycle.forecast.as_ref().is_none_or(|ele| ele.day < today) {
+        let daily_list = if today > cycle.current_cycle_start_date {
+            sum_cycle_daily_traffic(app_state, cycle, cycle.current_cycle_start_date, today - Duration::days(1)).await?
+        } else {
+            vec![]
+        };
+        let (uplink_traffic_usage, downlink_traffic_usage) =
+            daily_list.iter().fold((0, 0), |(up, down), (_, uplink, downlink)| (up + uplink, down + downlink));
+        let daily_list = daily_list
+            .iter()
+            .map(|(day, uplink, downlink)| (*day, cycle.statistic_method.traffic_usage(*uplink, *downlink)))
+            .collect::<Vec<(NaiveDate, i64)>>();
+        cycle.forecast = Some(CycleForecast {
+            day: today,
+            history_days: (today - cycle.current_cycle_start_date).num_days() as usize,
+            history_traffic_usage: cycle.statistic_method.traffic_usage(uplink_traffic_usage, downlink_traffic_usage),
+            history_uplink_traffic_usage: uplink_traffic_usage,
+            history_downlink_traffic_usage: downlink_traffic_usage,
+            expected_list: forecast_expected_list(&daily_list, cycle.current_cycle_start_date, today, cycle.current_cycle_end_date),
+        });
+    }
+    let projected = cycle.forecast.as_ref().and_then(|ele| {
+        let today_traffic_usage =
+            ele.today_traffic_usage(&cycle.statistic_method, cycle.uplink_traffic_usage, cycle.downlink_traffic_usage);
+        ele.project(cycle.traffic_usage, today_traffic_usage, cycle.traffic_limit)
+    });
+    cycle.projected_traffic_usage = projected.map(|(projected_traffic_usage, _)| projected_traffic_usage);
+    cycle.projected_exceed_date = projected.and_then(|(_, exceed_date)| exceed_date);
+    anyhow::Ok(())
+}
+
+/// 按已结束各天的日均计入流量预计 day 到 end_date 每天的用量，没有数据的天按 0 计算
+/// 已有完整一周的数据时按星期调整，使用相同星期的日均用量
+fn forecast_expected_list(daily_list: &[(NaiveDate, i64)], start_date: NaiveDate, day: NaiveDate, end_date: NaiveDate) -> Vec<i64> {
+    let history_days = (day - start_date).num_days();
+    if history_days <= 0 {
+        return vec![0; ((end_date - day).num_days() + 1) as usize];
+    }
+    let average = daily_list.iter().map(|(_, usage)| usage).sum::<i64>() / history_days;
+    let weekday_average = |weekday: Weekday| {
+        let weekday_days = start_date
+            .iter_days()
+            .take(history_days as usize)
+            .filter(|ele| ele.weekday() == weekday)
+            .count() as i64;
+        daily_list.iter().filter(|(ele, _)| ele.weekday() == weekday).map(|(_, usage)| usage).sum::<i64>() / weekday_days
+    };
+    day.iter_days()
+        .take_while(|ele| *ele <= end_date)
+        .map(|ele| if history_days >= 7 { weekday_average(ele.weekday()) } else { average })
+        .collect()
+}
+
+/// 按周期日统计 start_date 到 end_date 每天的上下行流量，不含免费时段，配置了 reset_hour 时周期日从 UTC 的 reset_hour 开始
+async fn sum_cycle_daily_traffic(
+    app_state: &AppState,
+    cycle: &CycleAppState,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> anyhow::Result<Vec<(NaiveDate, i64, i64)>> {
+    let mut daily_list: Vec<(NaiveDate, i64, i64)> = vec![];
+    let mut add = |day: NaiveDate, uplink: i64, downlink: i64| match daily_list.iter_mut().find(|(ele, _, _)| *ele == day) {
+        Some((_, up, down)) => {
+            *up += uplink;
+            *down += downlink;
+        }
+        None => daily_list.push((day, uplink, downlink)),
+    };
+    if cycle.free_windows.is_empty() && cycle.reset_hour.is_none() {
+        for ele in monitor_day_mapper::list_daterange_data(None, start_date, end_date, &app_state.db_pool).await? {
+            if cycle.network_name.contains(ele.network_name.as_ref().unwrap()) {
+                add(ele.day.unwrap(), ele.uplink_traffic_usage.unwrap(), ele.downlink_traffic_usage.unwrap());
+            }
+        }
+        return anyhow::Ok(daily_list);
+    }
+    let tz = app_state.config.tz();
+    let hour_data_list = monitor_hour_mapper::sum_timerange_group_by_hour(
+        &cycle.network_name,
+        time_util::cycle_day_start(start_date, cycle.reset_hour, tz),
+        time_util::cycle_day_start(end_date + Duration::days(1), cycle.reset_hour, tz),
+        &app_state.db_pool,
+    )
+    .await?;
+    for (day, hour, uplink, downlink) in hour_data_list {
+        if cycle.free_windows.iter().any(|ele| ele.contains(day.weekday(), hour)) {
+            continue;
+        }
+        let cycle_day = match cycle.reset_hour {
+            Some(reset_hour) => {
+                (time_util::to_utc(day.and_hms_opt(hour, 0, 0).unwrap(), tz) - Duration::hours(reset_hour as i64)).date()
+            }
+            None => day,
+        };
+        add(cycle_day, uplink, downlink);
+    }
+    anyhow::Ok(daily_list)
+}
+
+/// 统计流量周期日期范围内的流量，返回 (计入上行, 计入下行, 免费上行, 免费下行)
+/// 没有免费时段和重置小时时直接累加天数据，否则按小时数据统计
+pub async fn sum_cycle_traffic(
+    app_state: &AppState,
+    network_name: &[String],
+    free_windows: &[FreeWindow],
+    reset_hour: Option<u32>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> anyhow::Result<(i64, i64, i64, i64)> {
+    if free_windows.is_empty() && reset_hour.is_none() {
+        let (uplink_traffic_usage, downlink_traffic_usage) =
+            monitor_day_mapper::sum_daterange_data(network_name, start_date, end_date, &app_state.db_pool)
+                .await?
+                .unwrap_or((0, 0));
+        return anyhow::Ok((uplink_traffic_usage, downlink_traffic_usage, 0, 0));
+    }
+    let tz = app_state.config.tz();
+    let hour_data_list = monitor_hour_mapper::sum_timerange_group_by_hour(
+        network_name,
+        time_util::cycle_day_start(start_date, reset_hour, tz),
+        time_util::cycle_day_start(end_date + Duration::days(1), reset_hour, tz),
+        &app_state.db_pool,
+    )
+    .await?;
+    anyhow::Ok(hour_data_list.iter().fold((0, 0, 0, 0), |(up, down, free_up, free_down), (day, hour, uplink, downlink)| {
+        if free_windows.iter().any(|ele| ele.contains(day.weekday(), *hour)) {
+            (up, down, free_up + uplink, free_down + downlink)
+        } else {
+            (up + uplink, down + downlink, free_up, free_down)
+        }
+    }))
+}
+
+/// 周期切换后把上一个周期的用量写入周期历史，应用停止期间跨越了周期时启动后补写，已写入或没有数据时跳过
+pub async fn archive_previous_cycle(
+    app_state: &AppState,
+    liftcycle: &TrafficCycleConfig,
+    cycle: &CycleAppState,
+) -> anyhow::Result<()> {
+    let pre_start = match time_util::previous_cycle_start_date(&cycle.cycle_type, cycle.current_cycle_start_date) {
+        Some(pre_start) => pre_start,
+        None => return anyhow::Ok(()),
+    };
+    let pre_end = cycle.current_cycle_start_date - chrono::Duration::days(1);
+    if monitor_cycle_mapper::get_cycle_data(&cycle.name, pre_start, pre_end, &app_state.db_pool).await?.is_some() {
+        return anyhow::Ok(());
+    }
+    let day_data_list = monitor_day_mapper::list_daterange_data(None, pre_start, pre_end, &app_state.db_pool)
+        .await?
+        .into_iter()
+        .filter(|ele| cycle.network_name.contains(ele.network_name.as_ref().unwrap()))
+        .collect::<Vec<MonitorDay>>();
+    if day_data_list.is_empty() {
+        return anyhow::Ok(());
+    }
+    let (uplink_traffic_usage, downlink_traffic_usage, free_uplink_traffic_usage, free_downlink_traffic_usage) =
+        sum_cycle_traffic(app_state, &cycle.network_name, &cycle.free_windows, cycle.reset_hour, pre_start, pre_end).await?;
+    let traffic_usage = cycle.statistic_method.traffic_usage(uplink_traffic_usage, downlink_traffic_usage);
+    let (traffic_rollover, uplink_rollover, downlink_rollover) = cycle_rollover(app_state, liftcycle, cycle, pre_start).await?;
+    let traffic_limit = cycle.traffic_limit.map(|limit| limit + traffic_rollover.unwrap_or(0));
+    let uplink_limit = cycle.uplink_limit.map(|limit| limit + uplink_rollover.unwrap_or(0));
+    let downlink_limit = cycle.downlink_limit.map(|limit| limit + downlink_rollover.unwrap_or(0));
+    let rate_p95 = match cycle.billing_mode {
+        CycleBillingMode::Percentile95 => Some(
+            cycle_rate_p95(
+                app_state,
+                cycle,
+                pre_start,
+                time_util::cycle_day_start(cycle.current_cycle_start_date, cycle.reset_hour, app_state.config.tz()),
+            )
+            .await?,
+        ),
+        CycleBillingMode::Volume => None,
+    };
+    let pricing = parse_pricing(&liftcycle.pricing, &cycle.billing_mode, traffic_limit)?;
+    let overage_cost = pricing.as_ref().map(|pricing| pricing.overage_cost(traffic_usage));
+    let notify_percent = monitor_notify_mapper::list_cycle_data(&cycle.name, pre_start, pre_end, &app_state.db_pool)
+        .await?
+        .into_iter()
+        .filter_map(|ele| {
+            // 只有百分比的阈值显示为 80，其他条件显示条件标识，非 total 方向带方向前缀
+            let condition = match (ele.condition, ele.cost, ele.percent) {
+                (Some(condition), _, _) => condition,
+                (None, Some(cost), _) => format!("cost:{}", cost),
+                (None, None, Some(percent)) => format!("percent:{}", percent),
+                _ => return None,
+            };
+            let condition = match condition.strip_prefix("percent:") {
+                Some(percent) if !percent.contains(',') => percent.to_string(),
+                _ => condition,
+            };
+            match ele.target.as_deref() {
+                Some("pacing" | "pacing_end" | "cycle_start" | "cycle_end") | None => None,
+                Some("total" | "cost") => Some(condition),
+                Some(target) => Some(format!("{}:{}", target, condition)),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    let monitor_cycle = MonitorCycle {
+        id: None,
+        create_time: None,
+        quota_name: Some(cycle.name.clone()),
+        cycle_start_date: Some(pre_start),
+        cycle_end_date: Some(pre_end),
+        network_name: Some(cycle.network_name.join(",")),
+        traffic_limit,
+        uplink_limit,
+        downlink_limit,
+        traffic_rollover,
+        uplink_rollover,
+        downlink_rollover,
+        billing_mode: Some(liftcycle.billing_mode.clone()),
+        committed_rate: cycle.committed_rate,
+        rate_p95,
+        statistic_method: Some(liftcycle.statistic_method.clone()),
+        uplink_traffic_usage: Some(uplink_traffic_usage),
+        downlink_traffic_usage: Some(downlink_traffic_usage),
+        traffic_usage: Some(traffic_usage),
+        overage_cost: overage_cost.map(|ele| ele.to_string()),
+        currency: pricing.map(|ele| ele.currency),
+        free_uplink_traffic_usage: Some(free_uplink_traffic_usage),
+        free_downlink_traffic_usage: Some(free_downlink_traffic_usage),
+        notify_percent: Some(notify_percent),
+        exceeded: Some(
+            [
+                (traffic_usage, traffic_limit),
+                (uplink_traffic_usage, uplink_limit),
+                (downlink_traffic_usage, downlink_limit),
+                (rate_p95.unwrap_or(0), cycle.committed_rate),
+            ]
+            .iter()
+            .any(|(usage, limit)| limit.is_some_and(|limit| *usage >= limit)) as u32,
+        ),
+    };
+    tracing::info!("保存流量周期历史: {:?}", &monitor_cycle);
+    monitor_cycle_mapper::create(monitor_cycle, &app_state.db_pool).await?;
+    anyhow::Ok(())
+}
+
+#[cfg(test)]
+mod cycle_svc_test {
+    use rust_decimal_macros::dec;
+
+    use crate::{config::app_config::PricingTierConfig, util::traffic_util::{GB, TB}};
+
+    use super::*;
+
+    #[test]
+    fn rollover_amount_test() {
+        assert_eq!(rollover_amount(100 * GB, 100, None), 100 * GB);
+        assert_eq!(rollover_amount(100 * GB, 50, Some(20 * GB)), 20 * GB);
+        assert_eq!(rollover_amount(30 * GB, 50, Some(20 * GB)), 15 * GB);
+        assert_eq!(rollover_amount(-GB, 100, None), 0);
+    }
+
+    #[test]
+    fn percentile_95_test() {
+        assert_eq!(percentile_95(&mut []), 0);
+        // 20 个采样去掉最高的 1 个
+        let mut rate_list = (1..=20).rev().collect::<Vec<i64>>();
+        assert_eq!(percentile_95(&mut rate_list), 19);
+        // 21 个采样去掉最高的 1 个
+        let mut rate_list = (1..=21).collect::<Vec<i64>>();
+        assert_eq!(percentile_95(&mut rate_list), 20);
+    }
+
+    #[test]
+    fn five_minute_rate_p95_test() {
+        // 每 5 分钟 300MB 即 8Mbps，停止期间的用量全部计入一个 5 分钟时也不影响结果
+        let mut usage_list = vec![(300_000_000, false); 40];
+        usage_list.push((100 * 300_000_000, true));
+        usage_list.extend(vec![(600_000_000, true); 10]);
+        assert_eq!(five_minute_rate_p95(&usage_list), 8_000_000);
+        // 只有估算数据时为 0
+        assert_eq!(five_minute_rate_p95(&[(300_000_000, true)]), 0);
+    }
+
+    #[test]
+    fn parse_pricing_test() {
+        // 没有配置 included 时以 traffic_limit 为免费额度
+        let pricing_config = PricingConfig {
+            currency: "USD".to_string(),
+            included: None,
+            price_per_gb: Some(dec!(0.01)),
+            tiers: None,
+        };
+        let pricing = parse_pricing(&Some(pricing_config.clone()), &CycleBillingMode::Volume, Some(100 * GB)).unwrap().unwrap();
+        assert_eq!(pricing.included, 100 * GB);
+        assert_eq!(pricing.tiers.len(), 1);
+        assert_eq!((pricing.tiers[0].up_to, pricing.tiers[0].price_per_gb), (None, dec!(0.01)));
+        assert!(parse_pricing(&Some(pricing_config.clone()), &CycleBillingMode::Volume, None).is_err());
+        assert!(parse_pricing(&Some(pricing_config.clone()), &CycleBillingMode::Percentile95, Some(GB)).is_err());
+        assert!(parse_pricing(&None, &CycleBillingMode::Volume, None).unwrap().is_none());
+
+        let pricing_config = PricingConfig {
+            currency: "USD".to_string(),
+            included: Some("1TB".to_string()),
+            price_per_gb: None,
+            tiers: Some(vec![
+                PricingTierConfig { up_to: Some("10GB".to_string()), price_per_gb: dec!(1) },
+                PricingTierConfig { up_to: Some("20GB".to_string()), price_per_gb: dec!(0.5) },
+                PricingTierConfig { up_to: None, price_per_gb: dec!(0.1) },
+            ]),
+        };
+        let pricing = parse_pricing(&Some(pricing_config), &CycleBillingMode::Volume, None).unwrap().unwrap();
+        assert_eq!(pricing.included, TB);
+        assert_eq!(pricing.tiers.iter().map(|ele| ele.up_to).collect::<Vec<_>>(), vec![Some(10 * GB), Some(20 * GB), None]);
+
+        let pricing_config = PricingConfig {
+            currency: "USD".to_string(),
+            included: None,
+            price_per_gb: None,
+            tiers: None,
+        };
+        assert!(parse_pricing(&Some(pricing_config), &CycleBillingMode::Volume, Some(GB)).is_err());
+        // up_to 需要按升序填写
+        let pricing_config = PricingConfig {
+            currency: "USD".to_string(),
+            included: None,
+            price_per_gb: None,
+            tiers: Some(vec![
+                PricingTierConfig { up_to: Some("20GB".to_string()), price_per_gb: dec!(1) },
+                PricingTierConfig { up_to: Some("10GB".to_string()), price_per_gb: dec!(0.5) },
+            ]),
+        };
+        assert!(parse_pricing(&Some(pricing_config), &CycleBillingMode::Volume, Some(GB)).is_err());
+    }
+
+    #[test]
+    fn forecast_expected_list_test() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        // 2024-08-05 为星期一，不足一周时按日均用量，缺数据的天按 0 计算
+        let daily_list = vec![(date("2024-08-05"), 10 * GB), (date("2024-08-07"), 20 * GB)];
+        assert_eq!(
+            forecast_expected_list(&daily_list, date("2024-08-05"), date("2024-08-08"), date("2024-08-10")),
+            vec![10 * GB, 10 * GB, 10 * GB]
+        );
+        // 满一周后按相同星期的日均用量，周末用量更高
+        let daily_list = date("2024-08-05")
+            .iter_days()
+            .take(14)
+            .map(|ele| (ele, if ele.weekday().num_days_from_monday() >= 5 { 30 * GB } else { 10 * GB }))
+            .collect::<Vec<(NaiveDate, i64)>>();
+        let expected_list = forecast_expected_list(&daily_list, date("2024-08-05"), date("2024-08-19"), date("2024-08-25"));
+        assert_eq!(expected_list, vec![10 * GB, 10 * GB, 10 * GB, 10 * GB, 10 * GB, 30 * GB, 30 * GB]);
+
+        // 剩余 100GB 分配到包含今天的 7 天，超出限制后每日额度为 0
+        let forecast = CycleForecast {
+            day: date("2024-08-19"),
+            history_days: 14,
+            history_traffic_usage: 200 * GB,
+            history_uplink_traffic_usage: 50 * GB,
+            history_downlink_traffic_usage: 200 * GB,
+            expected_list,
+        };
+        assert_eq!(daily_allowance(Some(270 * GB), &forecast, date("2024-08-25")), Some(10 * GB));
+        assert_eq!(daily_allowance(Some(150 * GB), &forecast, date("2024-08-25")), Some(0));
+        assert_eq!(daily_allowance(None, &forecast, date("2024-08-25")), None);
+    }
+}
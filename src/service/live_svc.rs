@@ -0,0 +1,73 @@
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{config::state::{AppState, LiveRate}, util::time_util};
+
+/// 每秒采集一次网卡读数，计算实时速率放入内存环形缓冲区，并推送给订阅者
+pub fn init(app_state: &AppState) {
+    let buffer_secs = match &app_state.config.web {
+        Some(web) if web.live_buffer_secs > 0 => web.live_buffer_secs,
+        _ => return,
+    };
+    let app_state = app_state.clone();
+    tracing::info!("开始采集实时速率，保留最近 {} 秒", buffer_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut pre_readings: HashMap<String, (Instant, u64, u64)> = HashMap::new();
+        loop {
+            interval.tick().await;
+            for network_name in &app_state.config.network_name {
+                let traffic = match app_state.traffic_source.read(network_name) {
+                    Ok(traffic) => traffic,
+                    Err(e) => {
+                        tracing::debug!("采集网卡 {} 实时速率出错: {:?}", network_name, e);
+                        continue;
+                    }
+                };
+                let now = Instant::now();
+                let pre = pre_readings.insert(network_name.clone(), (now, traffic.tx_bytes, traffic.rx_bytes));
+                let (pre_time, pre_tx_bytes, pre_rx_bytes) = match pre {
+                    Some(pre) => pre,
+                    None => continue,
+                };
+                // 计数器变小说明发生了重置，丢弃这一次的速率
+                if traffic.tx_bytes < pre_tx_bytes || traffic.rx_bytes < pre_rx_bytes {
+                    continue;
+                }
+                let elapsed = (now - pre_time).as_secs_f64();
+                if elapsed <= 0.0 {
+                    continue;
+                }
+                let rate = LiveRate {
+                    network_name: network_name.clone(),
+                    time: time_util::to_local(time_util::now(), app_state.config.tz()),
+                    uplink_rate: ((traffic.tx_bytes - pre_tx_bytes) as f64 / elapsed) as i64,
+                    downlink_rate: ((traffic.rx_bytes - pre_rx_bytes) as f64 / elapsed) as i64,
+                };
+                {
+                    let mut buffer = app_state.live.buffer.write().await;
+                    let list = buffer.entry(network_name.clone()).or_default();
+                    if list.len() >= buffer_secs {
+                        list.pop_front();
+                    }
+                    list.push_back(rate.clone());
+                }
+                // 没有订阅者时发送会失败，忽略即可
+                let _ = app_state.live.sender.send(rate);
+            }
+        }
+    });
+}
+
+pub async fn list(app_state: &AppState, network_name: Option<&str>) -> Vec<LiveRate> {
+    let buffer = app_state.live.buffer.read().await;
+    let mut list: Vec<LiveRate> = buffer
+        .iter()
+        .filter(|(name, _)| network_name.is_none_or(|network_name| network_name == name.as_str()))
+        .flat_map(|(_, list)| list.iter().cloned())
+        .collect();
+    list.sort_by_key(|ele| ele.time);
+    list
+}
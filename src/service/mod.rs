@@ -1,4 +1,9 @@
 pub mod signal_svc;
 pub mod systemstat_svc;
+pub mod traffic_source_svc;
 pub mod scheduler_svc;
-pub mod statistics_svc;
\ No newline at end of file
+pub mod statistics_svc;
+pub mod retention_svc;
+pub mod live_svc;
+pub mod action_svc;
+pub mod cycle_svc;
\ No newline at end of file
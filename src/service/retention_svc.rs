@@ -0,0 +1,49 @@
+use chrono::Duration;
+
+use crate::{
+    config::state::{AppState, CycleBillingMode},
+    mapper::{monitor_day_mapper, monitor_five_minute_mapper, monitor_hour_mapper, monitor_second_mapper},
+    util::time_util,
+};
+
+/// 按 config[retention] 删除过期数据，保留天数包含今天，0 为永久保存
+/// 调用方需持有 collect_lock，避免周期切换统计用量时数据被删除
+pub async fn prune(app_state: &AppState) -> anyhow::Result<()> {
+    let retention = &app_state.config.retention;
+    let tz = app_state.config.tz();
+    let today = time_util::today(tz);
+    let first_day = |days: i64| today - Duration::days(days - 1);
+
+    if retention.second_days > 0 {
+        let res = monitor_second_mapper::delete_by_date(time_util::day_start(first_day(retention.second_days), tz), &app_state.db_pool).await?;
+        tracing::info!("删除 {} 天前的秒级监控数据 {} 条", retention.second_days, res.rows_affected());
+    }
+    if retention.five_minute_days > 0 {
+        // 按 95 计费带宽计费的流量周期需要保留整个周期的 5 分钟采样
+        let mut day = first_day(retention.five_minute_days);
+        for cycle in app_state.cycles.read().await.iter().filter(|ele| ele.billing_mode == CycleBillingMode::Percentile95) {
+            day = std::cmp::min(day, cycle.current_cycle_start_date - Duration::days(1));
+        }
+        let res = monitor_five_minute_mapper::delete_by_date(time_util::day_start(day, tz), &app_state.db_pool).await?;
+        tracing::info!("删除 {} 之前的5分钟监控数据 {} 条", day, res.rows_affected());
+    }
+    if retention.hour_days > 0 {
+        // 配置了免费时段或重置小时的流量周期按小时数据统计用量，周期内的数据不能删除
+        let mut day = first_day(retention.hour_days);
+        for cycle in app_state.cycles.read().await.iter().filter(|ele| !ele.free_windows.is_empty() || ele.reset_hour.is_some()) {
+            day = std::cmp::min(day, cycle.current_cycle_start_date);
+        }
+        let res = monitor_hour_mapper::delete_by_date(day, &app_state.db_pool).await?;
+        tracing::info!("删除 {} 之前的小时监控数据 {} 条", day, res.rows_affected());
+    }
+    if retention.day_days > 0 {
+        // 当前流量周期的用量由天数据累加，周期内的数据不能删除
+        let mut day = first_day(retention.day_days);
+        for cycle in app_state.cycles.read().await.iter() {
+            day = std::cmp::min(day, cycle.current_cycle_start_date);
+        }
+        let res = monitor_day_mapper::delete_by_date(day, &app_state.db_pool).await?;
+        tracing::info!("删除 {} 之前的天监控数据 {} 条", day, res.rows_affected());
+    }
+    anyhow::Ok(())
+}
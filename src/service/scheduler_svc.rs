@@ -1,47 +1,62 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::Ok;
+use tokio::time::{Instant, MissedTickBehavior};
 use tokio_cron_scheduler::{Job, JobScheduler};
 
-use crate::{config::state::AppState, service::statistics_svc};
+use crate::{config::state::AppState, service::{retention_svc, statistics_svc}, util::time_util};
 
 pub async fn init(app_state: &AppState) -> anyhow::Result<()> {
     let sched = JobScheduler::new().await?;
     
+    collector(app_state);
+
+    // 按配置的时区执行，半点时区的整点与 UTC 的整点不同
+    let tz = app_state.config.tz();
     let app_state_clone = app_state.clone();
-    tracing::info!("开始收集监控数据");
-    sched.add(Job::new_cron_job_async_tz("0/15 * * * * ? ", chrono::Local, move |_uuid, _l| {
+    sched.add(Job::new_cron_job_async_tz("0 1 * * * ? ", tz, move |_uuid, _l| {
         let app_state = app_state_clone.clone();
         Box::pin(async move {
-            let res = statistics_svc::collect_second_data(&app_state).await;
-            if res.is_err() {
-                tracing::error!("收集秒级监控数据出错: {:?}", &res);
+            let _collect_guard = app_state.collect_lock.lock().await;
+            let statistic_hour_time = time_util::now() - chrono::Duration::hours(1);
+            for network_name in &app_state.config.network_name {
+                let res = statistics_svc::collect_hour_data(&app_state, network_name, statistic_hour_time).await;
+                if res.is_err() {
+                    tracing::error!("收集网卡 {} 小时监控数据出错: {:?}", network_name, &res);
+                }
             }
         })
     })?).await?;
 
     let app_state_clone = app_state.clone();
-    sched.add(Job::new_cron_job_async_tz("0 1 * * * ? ", chrono::Local, move |_uuid, _l| {
+    sched.add(Job::new_cron_job_async_tz("0 2 0 * * ? ", tz, move |_uuid, _l| {
         let app_state = app_state_clone.clone();
         Box::pin(async move {
-            let statistic_hour_time = chrono::Local::now() - chrono::Duration::hours(1);
-            let res = statistics_svc::collect_hour_data(&app_state, statistic_hour_time.naive_local()).await;
+            let statistic_date = time_util::today(app_state.config.tz()) - chrono::Duration::days(1);
+            {
+                let _collect_guard = app_state.collect_lock.lock().await;
+                for network_name in &app_state.config.network_name {
+                    let res = statistics_svc::collect_day_data(&app_state, network_name, statistic_date).await;
+                    if res.is_err() {
+                        tracing::error!("收集网卡 {} 天监控数据出错: {:?}", network_name, &res);
+                    }
+                }
+            }
+            let res = statistics_svc::tg_notify_daily_statistics(&app_state, statistic_date).await;
             if res.is_err() {
-                tracing::error!("收集小时监控数据出错: {:?}", &res);
+                tracing::error!("tg消息发送出错: {:?}", &res);
             }
         })
     })?).await?;
 
     let app_state_clone = app_state.clone();
-    sched.add(Job::new_cron_job_async_tz("0 2 0 * * ? ", chrono::Local, move |_uuid, _l| {
+    sched.add(Job::new_cron_job_async_tz("0 10 0 * * ? ", tz, move |_uuid, _l| {
         let app_state = app_state_clone.clone();
         Box::pin(async move {
-            let statistic_date = chrono::Local::now() - chrono::Duration::days(1);
-            let res = statistics_svc::collect_day_data(&app_state, statistic_date.date_naive()).await;
+            let _collect_guard = app_state.collect_lock.lock().await;
+            let res = retention_svc::prune(&app_state).await;
             if res.is_err() {
-                tracing::error!("收集天监控数据出错: {:?}", &res);
-            }
-            let res = statistics_svc::tg_notify_daily_statistics(&app_state, statistic_date.date_naive()).await;
-            if res.is_err() {
-                tracing::error!("tg消息发送出错: {:?}", &res);
+                tracing::error!("删除过期数据出错: {:?}", &res);
             }
         })
     })?).await?;
@@ -49,3 +64,33 @@ pub async fn init(app_state: &AppState) -> anyhow::Result<()> {
     sched.start().await?;
     Ok(())
 }
+
+/// 秒级数据采集使用单独的任务循环，上一次采集结束前不会开始下一次，避免重复计算用量，采集时持有 collect_lock 与手动修改数据互斥
+fn collector(app_state: &AppState) {
+    let app_state = app_state.clone();
+    let period = Duration::from_secs(app_state.config.sample_interval_secs);
+    tracing::info!("开始收集监控数据，采集间隔 {} 秒", period.as_secs());
+    tokio::spawn(async move {
+        // 对齐到采集间隔的整数倍，例如间隔 15 秒时在每分钟的 0 15 30 45 秒采集
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let delay = period - Duration::from_nanos((since_epoch.as_nanos() % period.as_nanos()) as u64);
+        let mut interval = tokio::time::interval_at(Instant::now() + delay, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut pre_tick: Option<Instant> = None;
+        loop {
+            let tick = interval.tick().await;
+            if let Some(pre_tick) = pre_tick {
+                let missed = ((tick - pre_tick).as_secs() / period.as_secs()).saturating_sub(1);
+                if missed > 0 {
+                    tracing::warn!("上一次采集耗时过长，跳过了 {} 次采集", missed);
+                }
+            }
+            pre_tick = Some(tick);
+            let _collect_guard = app_state.collect_lock.lock().await;
+            let res = statistics_svc::collect_second_data(&app_state).await;
+            if res.is_err() {
+                tracing::error!("收集秒级监控数据出错: {:?}", &res);
+            }
+        }
+    });
+}
@@ -1,138 +1,468 @@
 use std::str::FromStr;
 
 use anyhow::anyhow;
-use chrono::{Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
-use rust_decimal::{prelude::FromPrimitive, Decimal};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use chrono_tz::Tz;
+use rust_decimal::{prelude::{FromPrimitive, ToPrimitive}, Decimal};
 use rust_decimal_macros::dec;
 use serde_json::json;
 
 use crate::{
-    config::state::{AppState, CycleAppState, CycleNotifyAppState, CycleStatisticMethod, CycleType},
+    config::app_config::{ActionConfig, FreeWindowConfig, ResetDayConfig, TrafficCycleConfig},
+    config::state::{AppState, CycleAppState, CycleNotifyAppState, CycleNotifyTarget, CycleBillingMode, CyclePacing, CycleStatisticMethod, CycleType, FreeWindow, MonthDay},
     mapper::{
+        data_migration_mapper,
+        monitor_cycle_mapper,
         monitor_day_mapper::{self, MonitorDay},
+        monitor_five_minute_mapper::{self, MonitorFiveMinute},
         monitor_hour_mapper::{self, MonitorHour},
+        monitor_notify_mapper::{self, MonitorNotify},
+        monitor_reset_mapper::{self, MonitorReset},
         monitor_second_mapper::{self, MonitorSecond},
     },
-    service::systemstat_svc,
-    util::{command_util, http_util, tg_util},
+    service::{action_svc::{self, ActionContext}, cycle_svc},
+    util::{http_util, tg_util, time_util, traffic_util::{parse_traffic_limit, traffic_show, GB}},
 };
 
-const KB: i64 = 1024;
-const MB: i64 = KB * 1024;
-const GB: i64 = MB * 1024;
-const TB: i64 = GB * 1024;
+/// 距上次采集超过采集间隔的此倍数时视为应用停止过
+const GAP_INTERVAL_MULTIPLE: i64 = 4;
+
+/// 两次采集之间按 32 位计数器回绕计算的最大用量，超过时回绕不可信，按计数器重置处理
+const WRAP_MAX_USAGE: i64 = GB;
+
+const UTC_MIGRATION: &str = "local_time_to_utc";
 
 pub async fn frist_collect(app_state: &AppState) -> anyhow::Result<()> {
+    convert_to_utc(app_state).await?;
+    fill_network_name(app_state).await?;
     generate_cycle(app_state).await?;
-    let now = chrono::Local::now().naive_local();
-    let pre_data = monitor_second_mapper::get_pre_data(&app_state.db_pool).await?;
-    if let None = pre_data {
-        return anyhow::Ok(());
+    let now = time_util::now();
+    for network_name in &app_state.config.network_name {
+        let pre_data = monitor_second_mapper::get_pre_data(network_name, &app_state.db_pool).await?;
+        let pre_end_time = match pre_data {
+            Some(pre_data) => pre_data.end_time.unwrap(),
+            None => continue,
+        };
+        if now - pre_end_time >= chrono::Duration::seconds(app_state.config.sample_interval_secs as i64)
+            || now.minute() != pre_end_time.minute()
+        {
+            return collect_second_data(app_state).await;
+        }
     }
-    let pre_data = pre_data.unwrap();
-    let pre_end_time = pre_data.end_time.unwrap();
-    if now - pre_end_time < chrono::Duration::seconds(15) && now.minute() == pre_end_time.minute() {
+    anyhow::Ok(())
+}
+
+/// 多网卡版本之前的数据没有网卡名称，归属到配置中的第一个网卡
+async fn fill_network_name(app_state: &AppState) -> anyhow::Result<()> {
+    let network_name = &app_state.config.network_name[0];
+    monitor_second_mapper::fill_network_name(network_name, &app_state.db_pool).await?;
+    monitor_hour_mapper::fill_network_name(network_name, &app_state.db_pool).await?;
+    monitor_day_mapper::fill_network_name(network_name, &app_state.db_pool).await?;
+    anyhow::Ok(())
+}
+
+/// UTC 版本之前的采集时间为本地时间，按配置的时区转换为 UTC，并补全小时数据的开始时间
+async fn convert_to_utc(app_state: &AppState) -> anyhow::Result<()> {
+    if data_migration_mapper::exists(UTC_MIGRATION, &app_state.db_pool).await? {
         return anyhow::Ok(());
     }
-    collect_second_data(app_state).await?;
+    let tz = app_state.config.tz();
+    let mut tx = app_state.db_pool.begin().await?;
+    for ele in monitor_second_mapper::list_all(&mut tx).await? {
+        let start_time = time_util::to_utc(ele.start_time.unwrap(), tz);
+        let end_time = time_util::to_utc(ele.end_time.unwrap(), tz);
+        monitor_second_mapper::update_time(ele.id.unwrap(), start_time, end_time, &mut tx).await?;
+    }
+    for ele in monitor_reset_mapper::list_all(&mut tx).await? {
+        let reset_time = time_util::to_utc(ele.reset_time.unwrap(), tz);
+        monitor_reset_mapper::update_reset_time(ele.id.unwrap(), reset_time, &mut tx).await?;
+    }
+    for ele in monitor_hour_mapper::list_without_start_time(&mut tx).await? {
+        let start_time = time_util::to_utc(ele.day.unwrap().and_hms_opt(ele.hour.unwrap(), 0, 0).unwrap(), tz);
+        // 夏令时切换时换算后的小时可能与已有的小时冲突，用量合并到已有的小时上，避免从周期统计中丢失
+        let network_name = ele.network_name.clone().unwrap_or_default();
+        match monitor_hour_mapper::get_by_start_time(&network_name, start_time, &mut tx).await? {
+            Some(exists) => {
+                tracing::warn!(
+                    "网卡 {} 小时数据 {} {}时 换算为 UTC {} 后与已有数据冲突，用量合并到已有数据 上行: {} 下行: {}",
+                    network_name, ele.day.unwrap(), ele.hour.unwrap(), start_time,
+                    ele.uplink_traffic_usage.unwrap_or(0), ele.downlink_traffic_usage.unwrap_or(0),
+                );
+                monitor_hour_mapper::merge_into(exists.id.unwrap(), &ele, &mut tx).await?;
+            }
+            None => {
+                monitor_hour_mapper::update_start_time(ele.id.unwrap(), start_time, &mut tx).await?;
+            }
+        }
+    }
+    data_migration_mapper::create(UTC_MIGRATION, &mut tx).await?;
+    tx.commit().await?;
+    tracing::info!("历史监控数据已按时区 {} 转换为 UTC", tz);
     anyhow::Ok(())
 }
 
 pub async fn collect_second_data(app_state: &AppState) -> anyhow::Result<()> {
-    let traffic = systemstat_svc::traffic(&app_state.config.network_name)?;
+    if let Err(e) = rollover_cycles(app_state).await {
+        tracing::error!("切换流量周期出错: {:?}", e);
+    }
+    for network_name in &app_state.config.network_name {
+        if let Err(e) = collect_network_second_data(app_state, network_name).await {
+            tracing::error!("收集网卡 {} 秒级监控数据出错: {:?}", network_name, e);
+        }
+    }
+    anyhow::Ok(())
+}
+
+async fn collect_network_second_data(app_state: &AppState, network_name: &str) -> anyhow::Result<()> {
+    let traffic = app_state.traffic_source.read(network_name)?;
     let (uplink_traffic_readings, downlink_traffic_readings) =
-        (traffic.tx_bytes.0 as i64, traffic.rx_bytes.0 as i64);
+        (traffic.tx_bytes as i64, traffic.rx_bytes as i64);
+    // 依次为 上行包数 下行包数 上行错误数 下行错误数 上行丢包数 下行丢包数
+    let packets_readings = [
+        traffic.tx_packets,
+        traffic.rx_packets,
+        traffic.tx_errors,
+        traffic.rx_errors,
+        traffic.tx_drops,
+        traffic.rx_drops,
+    ]
+    .map(|ele| ele as i64);
 
-    let pre_data = monitor_second_mapper::get_pre_data(&app_state.db_pool).await?;
+    let pre_data = monitor_second_mapper::get_pre_data(network_name, &app_state.db_pool).await?;
 
-    let end_time = chrono::Local::now().naive_local();
-    let (start_time, time_interval, uplink_traffic_usage, downlink_traffic_usage);
+    let tz = app_state.config.tz();
+    let end_time = time_util::now();
+    let (start_time, time_interval, uplink_traffic_usage, downlink_traffic_usage, packets_usage);
     if let Some(pre_data) = pre_data {
         start_time = pre_data.end_time.unwrap();
         time_interval = (end_time - start_time).num_seconds();
         let pre_uplink_traffic_readings = pre_data.uplink_traffic_readings.unwrap();
         let pre_downlink_traffic_readings = pre_data.downlink_traffic_readings.unwrap();
-        uplink_traffic_usage = if pre_uplink_traffic_readings > uplink_traffic_readings {
-            uplink_traffic_readings
-        } else {
-            uplink_traffic_readings - pre_uplink_traffic_readings
-        };
-        downlink_traffic_usage = if pre_downlink_traffic_readings > downlink_traffic_readings {
-            downlink_traffic_readings
-        } else {
-            downlink_traffic_readings - pre_downlink_traffic_readings
+        let mut reset_reason = match (&pre_data.boot_id, &traffic.boot_id, pre_data.ifindex, traffic.ifindex) {
+            (Some(pre_boot_id), Some(boot_id), _, _) if pre_boot_id != boot_id => Some("系统重启"),
+            (_, _, Some(pre_ifindex), Some(ifindex)) if pre_ifindex != ifindex => Some("网卡重建"),
+            _ => None,
         };
+        let same_boot = pre_data.boot_id.is_some() && pre_data.boot_id == traffic.boot_id;
+        let uplink_reason;
+        (uplink_traffic_usage, uplink_reason) = counter_usage(
+            pre_uplink_traffic_readings,
+            uplink_traffic_readings,
+            reset_reason.is_some(),
+            same_boot,
+        );
+        let downlink_reason;
+        (downlink_traffic_usage, downlink_reason) = counter_usage(
+            pre_downlink_traffic_readings,
+            downlink_traffic_readings,
+            reset_reason.is_some(),
+            same_boot,
+        );
+        let pre_packets_readings = [
+            pre_data.uplink_packets_readings,
+            pre_data.downlink_packets_readings,
+            pre_data.uplink_errors_readings,
+            pre_data.downlink_errors_readings,
+            pre_data.uplink_drops_readings,
+            pre_data.downlink_drops_readings,
+        ];
+        // 旧版本没有记录包数读数，无法计算用量
+        packets_usage = std::array::from_fn::<i64, 6, _>(|i| {
+            pre_packets_readings[i]
+                .map(|pre| counter_usage(pre, packets_readings[i], reset_reason.is_some(), same_boot).0)
+                .unwrap_or(0)
+        });
+        reset_reason = reset_reason.or(uplink_reason).or(downlink_reason);
+        if let Some(reason) = reset_reason {
+            tracing::warn!(
+                "网卡 {} 计数器重置: {} 上行读数: {} -> {} 下行读数: {} -> {}",
+                network_name,
+                reason,
+                pre_uplink_traffic_readings,
+                uplink_traffic_readings,
+                pre_downlink_traffic_readings,
+                downlink_traffic_readings
+            );
+            let monitor_reset = MonitorReset {
+                id: None,
+                create_time: None,
+                network_name: Some(network_name.to_string()),
+                reset_time: Some(end_time),
+                reason: Some(reason.to_string()),
+                pre_boot_id: pre_data.boot_id.clone(),
+                boot_id: traffic.boot_id.clone(),
+                pre_ifindex: pre_data.ifindex,
+                ifindex: traffic.ifindex,
+                pre_uplink_traffic_readings: Some(pre_uplink_traffic_readings),
+                uplink_traffic_readings: Some(uplink_traffic_readings),
+                pre_downlink_traffic_readings: Some(pre_downlink_traffic_readings),
+                downlink_traffic_readings: Some(downlink_traffic_readings),
+            };
+            monitor_reset_mapper::create(monitor_reset, &app_state.db_pool).await?;
+        }
     } else {
         start_time = end_time;
         uplink_traffic_usage = 0;
         downlink_traffic_usage = 0;
+        packets_usage = [0; 6];
         time_interval = 0;
     }
     tracing::debug!(
-        "秒统计: {} ~ {} 上行: {} 下行: {}",
+        "秒统计: {} {} ~ {} 上行: {} 下行: {}",
+        network_name,
         &start_time.to_string(),
         end_time.to_string(),
         traffic_show(uplink_traffic_usage),
         traffic_show(downlink_traffic_usage)
     );
-    let monitor_second = MonitorSecond {
+    // 应用停止期间的用量跨越多个小时，无法得知具体分布，需要分摊后标记为估算数据
+    let gap_seconds = app_state.config.sample_interval_secs as i64 * GAP_INTERVAL_MULTIPLE;
+    let is_estimated = time_interval > gap_seconds && time_util::truncate_hour(start_time, tz) != time_util::truncate_hour(end_time, tz);
+    let segment_list = if is_estimated {
+        // gap_strategy 在读取配置时已校验
+        match app_state.config.gap_strategy.as_str() {
+            "start" => vec![(start_time, end_time)],
+            // 按 5 分钟切分，5 分钟统计和 95 计费带宽也能得到平均分布的用量
+            _ => split_by_hour(start_time, end_time, tz)
+                .into_iter()
+                .flat_map(|(segment_start_time, segment_end_time)| split_by_five_minute(segment_start_time, segment_end_time))
+                .collect(),
+        }
+    } else {
+        vec![(start_time, end_time)]
+    };
+    if is_estimated {
+        tracing::info!(
+            "网卡 {} 距上次采集 {} 秒，用量分摊到 {} 个时段",
+            network_name,
+            time_interval,
+            segment_list.len()
+        );
+    }
+    let uplink_traffic_usage_list = split_usage(uplink_traffic_usage, &segment_list);
+    let downlink_traffic_usage_list = split_usage(downlink_traffic_usage, &segment_list);
+    let packets_usage_list = packets_usage.map(|usage| split_usage(usage, &segment_list));
+    for (i, (segment_start_time, segment_end_time)) in segment_list.iter().enumerate() {
+        let monitor_second = MonitorSecond {
+            id: None,
+            create_time: None,
+            network_name: Some(network_name.to_string()),
+            start_time: Some(*segment_start_time),
+            end_time: Some(*segment_end_time),
+            uplink_traffic_readings: Some(uplink_traffic_readings),
+            downlink_traffic_readings: Some(downlink_traffic_readings),
+            uplink_traffic_usage: Some(uplink_traffic_usage_list[i]),
+            downlink_traffic_usage: Some(downlink_traffic_usage_list[i]),
+            time_interval: Some((*segment_end_time - *segment_start_time).num_seconds()),
+            is_corrected: Some(0),
+            boot_id: traffic.boot_id.clone(),
+            ifindex: traffic.ifindex,
+            is_estimated: Some(is_estimated as u32),
+            uplink_packets_readings: Some(packets_readings[0]),
+            downlink_packets_readings: Some(packets_readings[1]),
+            uplink_packets_usage: Some(packets_usage_list[0][i]),
+            downlink_packets_usage: Some(packets_usage_list[1][i]),
+            uplink_errors_readings: Some(packets_readings[2]),
+            downlink_errors_readings: Some(packets_readings[3]),
+            uplink_errors_usage: Some(packets_usage_list[2][i]),
+            downlink_errors_usage: Some(packets_usage_list[3][i]),
+            uplink_drops_readings: Some(packets_readings[4]),
+            downlink_drops_readings: Some(packets_readings[5]),
+            uplink_drops_usage: Some(packets_usage_list[4][i]),
+            downlink_drops_usage: Some(packets_usage_list[5][i]),
+        };
+        monitor_second_mapper::create(monitor_second, &app_state.db_pool).await?;
+    }
+
+    for (segment_start_time, _) in &segment_list {
+        collect_five_minute_data(app_state, network_name, *segment_start_time).await?;
+    }
+    let mut hour_list: Vec<NaiveDateTime> = segment_list
+        .iter()
+        .map(|(segment_start_time, _)| time_util::truncate_hour(*segment_start_time, tz))
+        .collect();
+    hour_list.dedup();
+    for hour in hour_list {
+        collect_hour_data(app_state, network_name, hour).await?;
+    }
+
+    let mut day_list: Vec<NaiveDate> = segment_list
+        .iter()
+        .map(|(segment_start_time, _)| time_util::to_local(*segment_start_time, tz).date())
+        .collect();
+    day_list.dedup();
+    for day in day_list {
+        collect_day_data(app_state, network_name, day).await?;
+    }
+
+    let usage_list = segment_list
+        .iter()
+        .enumerate()
+        .map(|(i, (segment_start_time, _))| (*segment_start_time, uplink_traffic_usage_list[i], downlink_traffic_usage_list[i]))
+        .collect::<Vec<(NaiveDateTime, i64, i64)>>();
+    verify_exceeds_limit(app_state, network_name, &usage_list).await?;
+
+    anyhow::Ok(())
+}
+
+/// 按配置时区的整点把时间段切分为多段，时间均为 UTC
+fn split_by_hour(start_time: NaiveDateTime, end_time: NaiveDateTime, tz: Tz) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut segment_list = vec![];
+    let mut segment_start_time = start_time;
+    while segment_start_time < end_time {
+        let segment_end_time = std::cmp::min(time_util::truncate_hour(segment_start_time, tz) + Duration::hours(1), end_time);
+        segment_list.push((segment_start_time, segment_end_time));
+        segment_start_time = segment_end_time;
+    }
+    if segment_list.is_empty() {
+        segment_list.push((start_time, end_time));
+    }
+    segment_list
+}
+
+/// 按 UTC 的整 5 分钟把时间段切分为多段
+fn split_by_five_minute(start_time: NaiveDateTime, end_time: NaiveDateTime) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut segment_list = vec![];
+    let mut segment_start_time = start_time;
+    while segment_start_time < end_time {
+        let segment_end_time = std::cmp::min(time_util::truncate_five_minute(segment_start_time) + Duration::minutes(5), end_time);
+        segment_list.push((segment_start_time, segment_end_time));
+        segment_start_time = segment_end_time;
+    }
+    if segment_list.is_empty() {
+        segment_list.push((start_time, end_time));
+    }
+    segment_list
+}
+
+/// 按各段时长比例分摊用量，最后一段取余数，保证总量不变
+fn split_usage(usage: i64, segment_list: &[(NaiveDateTime, NaiveDateTime)]) -> Vec<i64> {
+    let total_seconds = segment_list.iter().map(|(start, end)| (*end - *start).num_seconds()).sum::<i64>();
+    let mut usage_list = vec![];
+    let mut remain_usage = usage;
+    for (i, (start, end)) in segment_list.iter().enumerate() {
+        let segment_usage = if i == segment_list.len() - 1 || total_seconds == 0 {
+            remain_usage
+        } else {
+            (usage as i128 * (*end - *start).num_seconds() as i128 / total_seconds as i128) as i64
+        };
+        usage_list.push(segment_usage);
+        remain_usage -= segment_usage;
+    }
+    usage_list
+}
+
+/// 计算两次计数器读数之间的用量，返回用量和读数异常的原因
+/// 已确认重启或网卡重建时计数器从 0 开始，本次读数即为用量；
+/// 确认没有重启而读数变小，且上次读数接近 32 位上限、回绕后的用量不超过 WRAP_MAX_USAGE 时按回绕计算，
+/// 其他情况(例如驱动重载、网卡 down/up 清零 64 位计数器)只能按重置处理
+fn counter_usage(pre_readings: i64, readings: i64, reset: bool, same_boot: bool) -> (i64, Option<&'static str>) {
+    if reset {
+        return (readings, None);
+    }
+    if readings >= pre_readings {
+        return (readings - pre_readings, None);
+    }
+    let wrap_usage = u32::MAX as i64 + 1 - pre_readings + readings;
+    if same_boot && pre_readings <= u32::MAX as i64 && wrap_usage <= WRAP_MAX_USAGE {
+        return (wrap_usage, Some("计数器回绕"));
+    }
+    (readings, Some("计数器重置"))
+}
+
+pub async fn collect_five_minute_data(
+    app_state: &AppState,
+    network_name: &str,
+    statistic_time: NaiveDateTime,
+) -> anyhow::Result<()> {
+    let start_time = time_util::truncate_five_minute(statistic_time);
+    let end_time = start_time + chrono::Duration::minutes(5);
+    let res =
+        monitor_second_mapper::sum_timerange_data(&[network_name.to_string()], start_time, end_time, &app_state.db_pool).await?;
+    if res.is_none() {
+        return anyhow::Ok(());
+    }
+    let (uplink_traffic_usage, downlink_traffic_usage) = res.unwrap();
+    let is_estimated =
+        monitor_second_mapper::exists_estimated_data(network_name, start_time, end_time, &app_state.db_pool).await?;
+    let packets_usage =
+        monitor_second_mapper::sum_timerange_packets_data(network_name, start_time, end_time, &app_state.db_pool).await?;
+    tracing::debug!(
+        "5分钟统计: {} {} 上行: {} 下行: {}",
+        network_name,
+        &start_time.to_string(),
+        traffic_show(uplink_traffic_usage),
+        traffic_show(downlink_traffic_usage)
+    );
+    let mut monitor_five_minute = MonitorFiveMinute {
         id: None,
         create_time: None,
+        network_name: Some(network_name.to_string()),
         start_time: Some(start_time),
-        end_time: Some(end_time),
-        uplink_traffic_readings: Some(uplink_traffic_readings),
-        downlink_traffic_readings: Some(downlink_traffic_readings),
         uplink_traffic_usage: Some(uplink_traffic_usage),
         downlink_traffic_usage: Some(downlink_traffic_usage),
-        time_interval: Some(time_interval),
-        is_corrected: Some(0),
+        is_estimated: Some(is_estimated as u32),
+        uplink_packets_usage: Some(packets_usage.uplink_packets_usage),
+        downlink_packets_usage: Some(packets_usage.downlink_packets_usage),
+        uplink_errors_usage: Some(packets_usage.uplink_errors_usage),
+        downlink_errors_usage: Some(packets_usage.downlink_errors_usage),
+        uplink_drops_usage: Some(packets_usage.uplink_drops_usage),
+        downlink_drops_usage: Some(packets_usage.downlink_drops_usage),
     };
-    monitor_second_mapper::create(monitor_second, &app_state.db_pool).await?;
-
-    collect_hour_data(app_state, start_time).await?;
-
-    collect_day_data(app_state, start_time.date()).await?;
-
-    verify_exceeds_limit(app_state, (uplink_traffic_usage, downlink_traffic_usage)).await?;
-
+    let entity = monitor_five_minute_mapper::get_data(network_name, start_time, &app_state.db_pool).await?;
+    if let Some(entity) = entity {
+        monitor_five_minute.id = entity.id;
+        monitor_five_minute_mapper::update(monitor_five_minute, &app_state.db_pool).await?;
+    } else {
+        monitor_five_minute_mapper::create(monitor_five_minute, &app_state.db_pool).await?;
+    }
     anyhow::Ok(())
 }
 
 pub async fn collect_hour_data(
     app_state: &AppState,
+    network_name: &str,
     statistic_hour_time: NaiveDateTime,
 ) -> anyhow::Result<()> {
-    let start_time = statistic_hour_time
-        .with_minute(0)
-        .unwrap()
-        .with_second(0)
-        .unwrap()
-        .with_nanosecond(0)
-        .unwrap();
+    let start_time = time_util::truncate_hour(statistic_hour_time, app_state.config.tz());
     let end_time = start_time + chrono::Duration::hours(1);
-    let day = start_time.with_hour(0).unwrap();
+    let local_start_time = time_util::to_local(start_time, app_state.config.tz());
     let res =
-        monitor_second_mapper::sum_timerange_data(start_time, end_time, &app_state.db_pool).await?;
+        monitor_second_mapper::sum_timerange_data(&[network_name.to_string()], start_time, end_time, &app_state.db_pool).await?;
     if res.is_none() {
         return anyhow::Ok(());
     }
     let (uplink_traffic_usage, downlink_traffic_usage) = res.unwrap();
+    let is_estimated =
+        monitor_second_mapper::exists_estimated_data(network_name, start_time, end_time, &app_state.db_pool).await?;
+    let packets_usage =
+        monitor_second_mapper::sum_timerange_packets_data(network_name, start_time, end_time, &app_state.db_pool).await?;
     tracing::debug!(
-        "小时统计: {} {} 上行: {} 下行: {}",
-        &day.date().to_string(),
-        start_time.hour(),
+        "小时统计: {} {} {} 上行: {} 下行: {}",
+        network_name,
+        &local_start_time.date().to_string(),
+        local_start_time.hour(),
         traffic_show(uplink_traffic_usage),
         traffic_show(downlink_traffic_usage)
     );
     let mut monitor_hour = MonitorHour {
         id: None,
         create_time: None,
-        day: Some(day.date()),
-        hour: Some(start_time.hour()),
+        network_name: Some(network_name.to_string()),
+        start_time: Some(start_time),
+        day: Some(local_start_time.date()),
+        hour: Some(local_start_time.hour()),
         uplink_traffic_usage: Some(uplink_traffic_usage),
         downlink_traffic_usage: Some(downlink_traffic_usage),
+        is_estimated: Some(is_estimated as u32),
+        uplink_packets_usage: Some(packets_usage.uplink_packets_usage),
+        downlink_packets_usage: Some(packets_usage.downlink_packets_usage),
+        uplink_errors_usage: Some(packets_usage.uplink_errors_usage),
+        downlink_errors_usage: Some(packets_usage.downlink_errors_usage),
+        uplink_drops_usage: Some(packets_usage.uplink_drops_usage),
+        downlink_drops_usage: Some(packets_usage.downlink_drops_usage),
     };
-    let entity = monitor_hour_mapper::get_day_hour_data(day.date(), start_time.hour(), &app_state.db_pool).await?;
+    let entity = monitor_hour_mapper::get_hour_data(network_name, start_time, &app_state.db_pool).await?;
     if let Some(entity) = entity {
         monitor_hour.id = entity.id;
         monitor_hour_mapper::update(monitor_hour, &app_state.db_pool).await?;
@@ -144,15 +474,21 @@ pub async fn collect_hour_data(
 
 pub async fn collect_day_data(
     app_state: &AppState,
+    network_name: &str,
     statistic_date: NaiveDate,
 ) -> anyhow::Result<()> {
-    let res = monitor_hour_mapper::sum_day_data(statistic_date, &app_state.db_pool).await?;
+    let res = monitor_hour_mapper::sum_day_data(&[network_name.to_string()], statistic_date, &app_state.db_pool).await?;
     if res.is_none() {
         return anyhow::Ok(());
     }
     let (uplink_traffic_usage, downlink_traffic_usage) = res.unwrap();
+    let is_estimated =
+        monitor_hour_mapper::exists_estimated_day_data(network_name, statistic_date, &app_state.db_pool).await?;
+    let packets_usage =
+        monitor_hour_mapper::sum_day_packets_data(network_name, statistic_date, &app_state.db_pool).await?;
     tracing::debug!(
-        "天统计: {} 上行: {} 下行: {}",
+        "天统计: {} {} 上行: {} 下行: {}",
+        network_name,
         &statistic_date.to_string(),
         traffic_show(uplink_traffic_usage),
         traffic_show(downlink_traffic_usage)
@@ -160,25 +496,25 @@ pub async fn collect_day_data(
     let mut monitor_day = MonitorDay {
         id: None,
         create_time: None,
+        network_name: Some(network_name.to_string()),
         day: Some(statistic_date),
         uplink_traffic_usage: Some(uplink_traffic_usage),
         downlink_traffic_usage: Some(downlink_traffic_usage),
+        is_estimated: Some(is_estimated as u32),
+        uplink_packets_usage: Some(packets_usage.uplink_packets_usage),
+        downlink_packets_usage: Some(packets_usage.downlink_packets_usage),
+        uplink_errors_usage: Some(packets_usage.uplink_errors_usage),
+        downlink_errors_usage: Some(packets_usage.downlink_errors_usage),
+        uplink_drops_usage: Some(packets_usage.uplink_drops_usage),
+        downlink_drops_usage: Some(packets_usage.downlink_drops_usage),
     };
-    let entity = monitor_day_mapper::get_day_data(statistic_date, &app_state.db_pool).await?;
+    let entity = monitor_day_mapper::get_day_data(network_name, statistic_date, &app_state.db_pool).await?;
     if let Some(entity) = entity {
         monitor_day.id = entity.id;
         monitor_day_mapper::update(monitor_day, &app_state.db_pool).await?;
     } else {
         monitor_day_mapper::create(monitor_day, &app_state.db_pool).await?;
     }
-
-    let day = statistic_date - chrono::Duration::days(1);
-    monitor_second_mapper::delete_by_date(
-        day.and_time(NaiveTime::from_hms_milli_opt(0, 0, 0, 0).unwrap()),
-        &app_state.db_pool,
-    )
-    .await?;
-
     anyhow::Ok(())
 }
 
@@ -190,12 +526,13 @@ pub async fn tg_notify_daily_statistics(app_state: &AppState, day: NaiveDate) ->
     if !tg.daily_report {
         return anyhow::Ok(());
     }
-    let entity = match monitor_day_mapper::get_day_data(day, &app_state.db_pool).await? {
-        Some(entity) => entity,
-        None => return Err(anyhow!("未找到当天的统计数据")),
-    };
-    let uplink_traffic_usage = entity.uplink_traffic_usage.unwrap();
-    let downlink_traffic_usage = entity.downlink_traffic_usage.unwrap();
+    let day_data_list = monitor_day_mapper::list_daterange_data(None, day, day, &app_state.db_pool).await?;
+    if day_data_list.is_empty() {
+        return Err(anyhow!("未找到当天的统计数据"));
+    }
+    let (uplink_traffic_usage, downlink_traffic_usage) = day_data_list.iter().fold((0, 0), |(up, down), ele| {
+        (up + ele.uplink_traffic_usage.unwrap(), down + ele.downlink_traffic_usage.unwrap())
+    });
     let mut text = format!(
         "{}\n{} 上传: {} 下载: {}",
         &app_state.config.vps_name,
@@ -203,89 +540,98 @@ pub async fn tg_notify_daily_statistics(app_state: &AppState, day: NaiveDate) ->
         traffic_show(uplink_traffic_usage),
         traffic_show(downlink_traffic_usage)
     );
-    let cycle = app_state.cycle.read().await.clone();
-    if let Some(cycle) = cycle {
-        if cycle.current_cycle_end_date < chrono::Local::now().date_naive() {
-            return anyhow::Ok(());
+    if app_state.config.network_name.len() > 1 {
+        for ele in &day_data_list {
+            text = format!(
+                "{}\n  {} 上传: {} 下载: {}",
+                text,
+                ele.network_name.as_ref().unwrap(),
+                traffic_show(ele.uplink_traffic_usage.unwrap()),
+                traffic_show(ele.downlink_traffic_usage.unwrap())
+            );
         }
-        let yesterday_traffic_usage = match cycle.statistic_method {
-            CycleStatisticMethod::MaxInOut => {
-                std::cmp::max(uplink_traffic_usage, downlink_traffic_usage)
-            }
-            CycleStatisticMethod::OnlyOut => uplink_traffic_usage,
-            CycleStatisticMethod::SumInOut => uplink_traffic_usage + downlink_traffic_usage,
-        };
-        if cycle.current_cycle_start_date == chrono::Local::now().date_naive() {
-            let pre_start = match cycle.cycle_type {
-                CycleType::DAY(each, _) => {
-                    cycle.current_cycle_start_date - chrono::Duration::days(each)
-                }
-                CycleType::MONTH(each, _) => cycle
-                    .current_cycle_start_date
-                    .checked_sub_months(Months::new(each as u32))
-                    .unwrap(),
-                _ => return Err(anyhow!("cycle_type 不会出现此类型")),
+    }
+    let cycle_list = app_state.cycles.read().await.clone();
+    for cycle in &cycle_list {
+        if cycle.current_cycle_end_date < time_util::cycle_today(cycle.reset_hour, app_state.config.tz()) {
+            continue;
+        }
+        // 多个流量周期时每个周期单独一段，以名称开头
+        let quota_title = if cycle_list.len() > 1 { format!("\n[{}]", cycle.name) } else { String::new() };
+        let (uplink_traffic_usage, downlink_traffic_usage, free_uplink_traffic_usage, free_downlink_traffic_usage) =
+            cycle_svc::sum_cycle_traffic(app_state, &cycle.network_name, &cycle.free_windows, cycle.reset_hour, day, day).await?;
+        let yesterday_traffic_usage = cycle.statistic_method.traffic_usage(uplink_traffic_usage, downlink_traffic_usage);
+        if cycle.current_cycle_start_date == time_util::cycle_today(cycle.reset_hour, app_state.config.tz()) {
+            let pre_start = match time_util::previous_cycle_start_date(&cycle.cycle_type, cycle.current_cycle_start_date) {
+                Some(pre_start) => pre_start,
+                None => return Err(anyhow!("cycle_type 不会出现此类型")),
             };
             let pre_end = cycle.current_cycle_start_date - chrono::Duration::days(1);
-            let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage) =
-                monitor_day_mapper::sum_daterange_data(pre_start, pre_end, &app_state.db_pool)
-                    .await?
-                    .unwrap_or((0, 0));
-            let cycle_traffic_usage = match cycle.statistic_method {
-                CycleStatisticMethod::MaxInOut => std::cmp::max(
-                    cycle_day_uplink_traffic_usage,
-                    cycle_day_downlink_traffic_usage,
-                ),
-                CycleStatisticMethod::OnlyOut => cycle_day_uplink_traffic_usage,
-                CycleStatisticMethod::SumInOut => {
-                    cycle_day_uplink_traffic_usage + cycle_day_downlink_traffic_usage
-                }
-            };
+            let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage, cycle_day_free_uplink_traffic_usage, cycle_day_free_downlink_traffic_usage) =
+                cycle_svc::sum_cycle_traffic(app_state, &cycle.network_name, &cycle.free_windows, cycle.reset_hour, pre_start, pre_end).await?;
+            // 上一周期的限制可能包含结转流量，以周期历史中的限制为准
+            let mut pre_cycle = cycle.clone();
+            if let Some(monitor_cycle) = monitor_cycle_mapper::get_cycle_data(&cycle.name, pre_start, pre_end, &app_state.db_pool).await? {
+                pre_cycle.traffic_limit = monitor_cycle.traffic_limit;
+                pre_cycle.uplink_limit = monitor_cycle.uplink_limit;
+                pre_cycle.downlink_limit = monitor_cycle.downlink_limit;
+                pre_cycle.rate_p95 = monitor_cycle.rate_p95;
+                pre_cycle.overage_cost = monitor_cycle.overage_cost.and_then(|ele| Decimal::from_str(&ele).ok());
+            }
+            let cycle_traffic_usage = cycle.statistic_method.traffic_usage(cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage);
             text = format!(
-                "{} 计入流量: {}\n{} ~ {} 上传: {} 下载: {} 计入流量: {}/{}\n上一周期已结束\n剩余流量 {}%",
+                "{}{} 计入流量: {}{}\n{} ~ {} 上传: {} 下载: {} 计入流量: {}{}{}{}\n上一周期已结束\n{}{}",
                 text,
+                quota_title,
                 traffic_show(yesterday_traffic_usage),
+                free_show(cycle, free_uplink_traffic_usage, free_downlink_traffic_usage),
                 pre_start,
                 pre_end,
-                traffic_show(cycle_day_uplink_traffic_usage),
-                traffic_show(cycle_day_downlink_traffic_usage),
-                traffic_show(cycle_traffic_usage),
-                traffic_show(cycle.traffic_limit),
-                format!("{:.0}", Decimal::from_i64(cycle.traffic_limit - cycle_traffic_usage).unwrap() / Decimal::from_i64(cycle.traffic_limit).unwrap() * Decimal::from_i64(100).unwrap())
+                usage_show(cycle_day_uplink_traffic_usage, pre_cycle.uplink_limit),
+                usage_show(cycle_day_downlink_traffic_usage, pre_cycle.downlink_limit),
+                usage_show(cycle_traffic_usage, pre_cycle.traffic_limit),
+                free_show(cycle, cycle_day_free_uplink_traffic_usage, cycle_day_free_downlink_traffic_usage),
+                rate_p95_show(&pre_cycle),
+                cost_show(&pre_cycle, pre_cycle.overage_cost),
+                remain_percent_show(&pre_cycle, (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage, cycle_traffic_usage)),
+                rollover_show(cycle),
             );
         } else {
-            let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage) =
-                monitor_day_mapper::sum_daterange_data(
+            let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage, cycle_day_free_uplink_traffic_usage, cycle_day_free_downlink_traffic_usage) =
+                cycle_svc::sum_cycle_traffic(
+                    app_state,
+                    &cycle.network_name,
+                    &cycle.free_windows,
+                    cycle.reset_hour,
                     cycle.current_cycle_start_date,
-                    chrono::Local::now().date_naive() - chrono::Duration::days(1),
-                    &app_state.db_pool,
+                    time_util::cycle_today(cycle.reset_hour, app_state.config.tz()) - chrono::Duration::days(1),
                 )
-                .await?
-                .unwrap_or((0, 0));
-            let cycle_traffic_usage = match cycle.statistic_method {
-                CycleStatisticMethod::MaxInOut => std::cmp::max(
-                    cycle_day_uplink_traffic_usage,
-                    cycle_day_downlink_traffic_usage,
-                ),
-                CycleStatisticMethod::OnlyOut => cycle_day_uplink_traffic_usage,
-                CycleStatisticMethod::SumInOut => {
-                    cycle_day_uplink_traffic_usage + cycle_day_downlink_traffic_usage
-                }
-            };
-            let remain_day = (cycle.current_cycle_end_date - chrono::Local::now().date_naive()).num_days() + 1;
+                .await?;
+            let cycle_traffic_usage = cycle.statistic_method.traffic_usage(cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage);
+            // 每日通知可能早于当天第一次采集，按今天重新计算预测
+            let mut forecast_cycle = cycle.clone();
+            cycle_svc::update_forecast(app_state, &mut forecast_cycle).await?;
+            let remain_day = (cycle.current_cycle_end_date - time_util::cycle_today(cycle.reset_hour, app_state.config.tz())).num_days() + 1;
             let total_day = (cycle.current_cycle_end_date - cycle.current_cycle_start_date).num_days() + 1;
             text = format!(
-                "{} 计入流量: {}\n{} ~ {} 上传: {} 下载: {} 计入流量: {}/{}\n距下次重置: {}天\n剩余流量 {}% 剩余周期 {}%",
+                "{}{} 计入流量: {}{}\n{} ~ {} 上传: {} 下载: {} 计入流量: {}{}{}{}{}{}{}\n距下次重置: {}天\n{} 剩余周期 {}%",
                 text,
+                quota_title,
                 traffic_show(yesterday_traffic_usage),
+                free_show(cycle, free_uplink_traffic_usage, free_downlink_traffic_usage),
                 cycle.current_cycle_start_date,
                 cycle.current_cycle_end_date,
-                traffic_show(cycle_day_uplink_traffic_usage),
-                traffic_show(cycle_day_downlink_traffic_usage),
-                traffic_show(cycle_traffic_usage),
-                traffic_show(cycle.traffic_limit),
+                usage_show(cycle_day_uplink_traffic_usage, cycle.uplink_limit),
+                usage_show(cycle_day_downlink_traffic_usage, cycle.downlink_limit),
+                usage_show(cycle_traffic_usage, cycle.traffic_limit),
+                free_show(cycle, cycle_day_free_uplink_traffic_usage, cycle_day_free_downlink_traffic_usage),
+                rate_p95_show(cycle),
+                cost_show(cycle, cycle.pricing.as_ref().map(|pricing| pricing.overage_cost(cycle_traffic_usage))),
+                rollover_show(cycle),
+                forecast_show(&forecast_cycle),
+                pacing_show(&forecast_cycle),
                 remain_day,
-                format!("{:.0}", Decimal::from_i64(cycle.traffic_limit - cycle_traffic_usage).unwrap() / Decimal::from_i64(cycle.traffic_limit).unwrap() * Decimal::from_i64(100).unwrap()),
+                remain_percent_show(cycle, (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage, cycle_traffic_usage)),
                 format!("{:.0}", Decimal::from_i64(remain_day).unwrap() / Decimal::from_i64(total_day).unwrap() * Decimal::from_i64(100).unwrap()),
             );
         }
@@ -301,118 +647,497 @@ pub async fn tg_notify_daily_statistics(app_state: &AppState, day: NaiveDate) ->
     anyhow::Ok(())
 }
 
-fn traffic_show<T: Into<Decimal>>(bytes: T) -> String {
-    let bytes = bytes.into();
-    if bytes < Decimal::from_i64(KB).unwrap() {
-        return format!("{} B", bytes);
-    } else if bytes < Decimal::from_i64(MB).unwrap() {
-        return format!("{:.2} KB", bytes / Decimal::from_i64(KB).unwrap());
-    } else if bytes < Decimal::from_i64(GB).unwrap() {
-        return format!("{:.2} MB", bytes / Decimal::from_i64(MB).unwrap());
-    } else if bytes < Decimal::from_i64(TB).unwrap() {
-        return format!("{:.2} GB", bytes / Decimal::from_i64(GB).unwrap());
+/// 配置了限制时显示为 用量/限制
+fn usage_show(usage: i64, limit: Option<i64>) -> String {
+    match limit {
+        Some(limit) => format!("{}/{}", traffic_show(usage), traffic_show(limit)),
+        None => traffic_show(usage),
+    }
+}
+
+/// 各项已配置限制的剩余百分比
+fn remain_percent_show(cycle: &CycleAppState, (uplink_traffic_usage, downlink_traffic_usage, traffic_usage): (i64, i64, i64)) -> String {
+    [
+        ("剩余流量", traffic_usage, cycle.traffic_limit),
+        ("剩余上行流量", uplink_traffic_usage, cycle.uplink_limit),
+        ("剩余下行流量", downlink_traffic_usage, cycle.downlink_limit),
+    ]
+    .iter()
+    .filter_map(|(label, usage, limit)| {
+        limit.map(|limit| {
+            format!(
+                "{} {:.0}%",
+                label,
+                Decimal::from_i64(limit - usage).unwrap() / Decimal::from_i64(limit).unwrap() * dec!(100)
+            )
+        })
+    })
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+fn rate_show(rate: i64) -> String {
+    let rate = Decimal::from_i64(rate).unwrap();
+    if rate < dec!(1000000) {
+        format!("{:.2} Kbps", rate / dec!(1000))
+    } else if rate < dec!(1000000000) {
+        format!("{:.2} Mbps", rate / dec!(1000000))
     } else {
-        return format!("{:.2} TB", bytes / Decimal::from_i64(TB).unwrap());
+        format!("{:.2} Gbps", rate / dec!(1000000000))
+    }
+}
+
+/// 按 95 计费带宽计费时显示 95 计费带宽和承诺带宽
+fn rate_p95_show(cycle: &CycleAppState) -> String {
+    match (&cycle.billing_mode, cycle.committed_rate) {
+        (CycleBillingMode::Percentile95, Some(committed_rate)) => format!(
+            "\n95计费带宽: {}/{}",
+            rate_show(cycle.rate_p95.unwrap_or(0)),
+            rate_show(committed_rate)
+        ),
+        _ => String::new(),
+    }
+}
+
+/// 可以预测时显示预计周期用量和超出限制的日期
+fn forecast_show(cycle: &CycleAppState) -> String {
+    match cycle.projected_traffic_usage {
+        Some(projected_traffic_usage) => format!(
+            "\n预计周期用量: {}{}",
+            usage_show(projected_traffic_usage, cycle.traffic_limit),
+            cycle.projected_exceed_date.map(|ele| format!(" 预计 {} 超出", ele)).unwrap_or_default()
+        ),
+        None => String::new(),
+    }
+}
+
+/// 配置了每日额度时显示今天的每日额度
+fn pacing_show(cycle: &CycleAppState) -> String {
+    match (&cycle.pacing, &cycle.forecast) {
+        (Some(_), Some(forecast)) => cycle_svc::daily_allowance(cycle.traffic_limit, forecast, cycle.current_cycle_end_date)
+            .map(|allowance| format!("\n今日额度: {}", traffic_show(allowance)))
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// 配置了超额计费时显示超额费用
+fn cost_show(cycle: &CycleAppState, overage_cost: Option<Decimal>) -> String {
+    match (&cycle.pricing, overage_cost) {
+        (Some(pricing), Some(overage_cost)) => format!("\n超额费用: {} {}", overage_cost, pricing.currency),
+        _ => String::new(),
     }
 }
 
 pub async fn verify_exceeds_limit(
     app_state: &AppState,
-    (uplink_traffic_usage, downlink_traffic_usage): (i64, i64),
+    network_name: &str,
+    usage_list: &[(NaiveDateTime, i64, i64)],
 ) -> anyhow::Result<()> {
-    let config = &app_state.config;
-    if config.traffic_cycle.is_none() {
+    if app_state.config.traffic_cycle.is_none() {
         return anyhow::Ok(());
     }
-    let mut cycle = app_state.cycle.read().await.clone().unwrap();
-    if cycle.current_cycle_end_date < chrono::Local::now().date_naive() {
-        if let CycleType::ONCE(_, _) = cycle.cycle_type {
-            return anyhow::Ok(());
+    let mut cycle_list = app_state.cycles.read().await.clone();
+    for cycle in cycle_list.iter_mut() {
+        if !cycle.network_name.iter().any(|ele| ele == network_name) {
+            continue;
         }
-        generate_cycle(app_state).await?;
-        cycle = app_state.cycle.read().await.clone().unwrap();
+        verify_cycle_exceeds_limit(app_state, cycle, usage_list).await;
     }
-    cycle.uplink_traffic_usage = cycle.uplink_traffic_usage + uplink_traffic_usage;
-    cycle.downlink_traffic_usage = cycle.downlink_traffic_usage + downlink_traffic_usage;
-    cycle.traffic_usage = match cycle.statistic_method {
-        CycleStatisticMethod::MaxInOut => {
-            std::cmp::max(cycle.uplink_traffic_usage, cycle.downlink_traffic_usage)
+    *app_state.cycles.write().await = cycle_list;
+    anyhow::Ok(())
+}
+
+/// 每次采集前判断各流量周期是否需要切换，新周期的用量由数据库重新统计，此时本次采样还未写入，之后各网卡的采样再分别累加
+async fn rollover_cycles(app_state: &AppState) -> anyhow::Result<()> {
+    let cycle_config_list = match &app_state.config.traffic_cycle {
+        Some(cycle_config_list) => cycle_config_list,
+        None => return anyhow::Ok(()),
+    };
+    let mut cycle_list = app_state.cycles.read().await.clone();
+    // 各流量周期相互独立，分别判断是否需要切换周期
+    for (cycle, cycle_config) in cycle_list.iter_mut().zip(cycle_config_list) {
+        if cycle.current_cycle_end_date >= time_util::cycle_today(cycle.reset_hour, app_state.config.tz()) {
+            continue;
         }
-        CycleStatisticMethod::OnlyOut => cycle.uplink_traffic_usage,
-        CycleStatisticMethod::SumInOut => cycle.uplink_traffic_usage + cycle.downlink_traffic_usage,
+        if let CycleType::ONCE(_, _) = cycle.cycle_type {
+            continue;
+        }
+        *cycle = build_cycle(app_state, cycle_config).await?;
+        tracing::info!(
+            "流量周期 {} 进入新周期 {} ~ {}，阈值已重置",
+            cycle.name,
+            cycle.current_cycle_start_date,
+            cycle.current_cycle_end_date
+        );
+        run_cycle_hooks(app_state, cycle_config, cycle).await?;
+    }
+    *app_state.cycles.write().await = cycle_list;
+    anyhow::Ok(())
+}
+
+async fn verify_cycle_exceeds_limit(
+    app_state: &AppState,
+    cycle: &mut CycleAppState,
+    usage_list: &[(NaiveDateTime, i64, i64)],
+) {
+    let config = &app_state.config;
+    // 多个流量周期时通知中带上周期名称
+    let quota_title = if config.traffic_cycle.as_ref().is_some_and(|ele| ele.len() > 1) {
+        format!("[{}] ", cycle.name)
+    } else {
+        String::new()
     };
-    let traffic_limit = Decimal::from_i64(cycle.traffic_limit).unwrap();
-    let traffic_usage = Decimal::from_i64(cycle.traffic_usage).unwrap();
+    // 按采集时间所在的小时区分免费时段的流量
+    for (time, uplink_traffic_usage, downlink_traffic_usage) in usage_list {
+        if cycle.is_free_time(*time, config.tz()) {
+            cycle.free_uplink_traffic_usage += uplink_traffic_usage;
+            cycle.free_downlink_traffic_usage += downlink_traffic_usage;
+        } else {
+            cycle.uplink_traffic_usage += uplink_traffic_usage;
+            cycle.downlink_traffic_usage += downlink_traffic_usage;
+        }
+    }
+    cycle.traffic_usage = cycle.statistic_method.traffic_usage(cycle.uplink_traffic_usage, cycle.downlink_traffic_usage);
+    cycle.overage_cost = cycle.pricing.as_ref().map(|pricing| pricing.overage_cost(cycle.traffic_usage));
+    if let Err(e) = cycle_svc::update_forecast(app_state, cycle).await {
+        tracing::error!("计算流量周期 {} 的用量预测失败: {:?}", cycle.name, e);
+    }
+    if cycle.billing_mode == CycleBillingMode::Percentile95 {
+        // 进入新的 5 分钟后重新计算 95 计费带宽，只统计已结束的采样
+        if let Some((time, _, _)) = usage_list.last() {
+            let rate_p95_time = time_util::truncate_five_minute(*time);
+            if cycle.rate_p95_time.is_none_or(|ele| ele < rate_p95_time) {
+                match cycle_svc::cycle_rate_p95(app_state, cycle, cycle.current_cycle_start_date, rate_p95_time).await {
+                    Ok(rate_p95) => {
+                        cycle.rate_p95 = Some(rate_p95);
+                        cycle.rate_p95_time = Some(rate_p95_time);
+                    }
+                    Err(e) => tracing::error!("计算流量周期 {} 的95计费带宽失败: {:?}", cycle.name, e),
+                }
+            }
+        }
+    }
     tracing::debug!(
-        "流量周期统计: 已用量: {} 限制: {}",
-        traffic_show(traffic_usage),
-        traffic_show(traffic_limit)
+        "流量周期 {} 统计: 已用量: {} 上行: {} 下行: {}",
+        cycle.name,
+        usage_show(cycle.traffic_usage, cycle.traffic_limit),
+        usage_show(cycle.uplink_traffic_usage, cycle.uplink_limit),
+        usage_show(cycle.downlink_traffic_usage, cycle.downlink_limit)
     );
+    cycle_svc::verify_cycle_pacing(app_state, cycle, &quota_title).await;
+    // notify 按百分比升序排列，依次处理所有已越过的阈值，各方向的阈值分别判断
+    let remain_day = (cycle.current_cycle_end_date - time_util::cycle_today(cycle.reset_hour, config.tz())).num_days() + 1;
+    let cycle_context = ActionContext {
+        event: "notify".to_string(),
+        quota_name: cycle.name.clone(),
+        cycle_start_date: cycle.current_cycle_start_date,
+        cycle_end_date: cycle.current_cycle_end_date,
+        ..Default::default()
+    };
+    // 同一次采集越过的多个阈值按升序依次执行动作
+    let mut action_list = vec![];
     for notify in &mut cycle.notify {
-        if traffic_usage >= traffic_limit / dec!(100) * Decimal::from_u8(notify.percent).unwrap() {
-            if !notify.finished {
-                tracing::warn!("{} 流量使用超{}%", config.vps_name, notify.percent);
-                let text = format!(
-                    "{} 流量使用超{}% {}/{}",
-                    config.vps_name,
-                    notify.percent,
-                    traffic_show(traffic_usage),
-                    traffic_show(traffic_limit)
-                );
-                tg_util::send_msg(config, text).await;
-                if let Some(exec) = &notify.exec {
-                    tracing::info!("流量使用超出限制，执行命令: {}", exec);
-                    match command_util::execute_to_output(".".to_string(), vec![exec.clone()]).await {
-                        Ok(res) => {
-                            if res.status.success() {
-                                tracing::info!(
-                                    "执行命令成功，执行结果: {}",
-                                    String::from_utf8_lossy(&res.stdout)
-                                )
-                            } else {
-                                tracing::info!(
-                                    "执行命令失败，执行结果: {}",
-                                    String::from_utf8_lossy(&res.stderr)
-                                )
-                            }
-                        }
-                        Err(e) => tracing::info!("命令提交失败: {:?}", e),
-                    }
-                }
-                notify.finished = true;
+        if notify.finished || notify.remaining_days.is_some_and(|remaining_days| remain_day > remaining_days) {
+            continue;
+        }
+        // 超额费用阈值按金额判断，pricing 和 cost 在生成周期时已校验
+        if let (CycleNotifyTarget::Cost, Some(pricing), Some(cost)) = (&notify.target, &cycle.pricing, notify.cost) {
+            let overage_cost = cycle.overage_cost.unwrap_or_default();
+            if overage_cost < cost {
+                continue;
+            }
+            tracing::warn!("{} {}超额费用超{} {}", config.vps_name, quota_title, cost, pricing.currency);
+            let text = format!(
+                "{} {}超额费用超{} {} {}/{} {}",
+                config.vps_name,
+                quota_title,
+                cost,
+                pricing.currency,
+                overage_cost,
+                cost,
+                pricing.currency
+            );
+            let context = ActionContext { usage: Some(overage_cost), limit: Some(cost), message: Some(text), ..cycle_context.clone() };
+            if let Err(e) = finish_notify(app_state, context, notify, &mut action_list).await {
+                tracing::error!("保存阈值触发记录失败，下次采集时重试: {:?}", e);
+            }
+            continue;
+        }
+        // 预计周期用量越过阈值且实际用量还没有越过时提前通知
+        if notify.target == CycleNotifyTarget::Forecast {
+            let (traffic_limit, projected_traffic_usage) = match (cycle.traffic_limit, cycle.projected_traffic_usage) {
+                (Some(traffic_limit), Some(projected_traffic_usage)) => (traffic_limit, projected_traffic_usage),
+                _ => continue,
+            };
+            let threshold = Decimal::from_i64(traffic_limit).unwrap() / dec!(100) * notify.percent;
+            if Decimal::from_i64(projected_traffic_usage).unwrap() < threshold
+                || Decimal::from_i64(cycle.traffic_usage).unwrap() >= threshold
+            {
+                continue;
+            }
+            tracing::warn!("{} {}预计流量使用超{}%", config.vps_name, quota_title, notify.percent.normalize());
+            let text = format!(
+                "{} {}预计流量使用超{}% 预计周期用量: {}/{}{}",
+                config.vps_name,
+                quota_title,
+                notify.percent.normalize(),
+                traffic_show(projected_traffic_usage),
+                traffic_show(traffic_limit),
+                cycle.projected_exceed_date.map(|ele| format!(" 预计 {} 超出", ele)).unwrap_or_default()
+            );
+            let context = ActionContext {
+                usage: Some(Decimal::from(projected_traffic_usage)),
+                limit: Some(Decimal::from(traffic_limit)),
+                message: Some(text),
+                ..cycle_context.clone()
+            };
+            if let Err(e) = finish_notify(app_state, context, notify, &mut action_list).await {
+                tracing::error!("保存阈值触发记录失败，下次采集时重试: {:?}", e);
             }
-            break;
+            continue;
+        }
+        // 阈值对应方向的限制在生成周期时已校验
+        let (label, traffic_usage, traffic_limit) = match (&cycle.billing_mode, &notify.target) {
+            (CycleBillingMode::Percentile95, _) => ("95计费带宽", cycle.rate_p95.unwrap_or(0), cycle.committed_rate.unwrap()),
+            (_, CycleNotifyTarget::Uplink) => ("上行流量使用", cycle.uplink_traffic_usage, cycle.uplink_limit.unwrap()),
+            (_, CycleNotifyTarget::Downlink) => ("下行流量使用", cycle.downlink_traffic_usage, cycle.downlink_limit.unwrap()),
+            (_, _) => ("流量使用", cycle.traffic_usage, cycle.traffic_limit.unwrap()),
+        };
+        let (usage_text, limit_text) = match cycle.billing_mode {
+            CycleBillingMode::Percentile95 => (rate_show(traffic_usage), rate_show(traffic_limit)),
+            CycleBillingMode::Volume => (traffic_show(traffic_usage), traffic_show(traffic_limit)),
+        };
+        if !notify.reached(traffic_usage, traffic_limit) {
+            continue;
+        }
+        let condition_text = notify_condition_show(notify);
+        tracing::warn!("{} {}{}{}", config.vps_name, quota_title, label, condition_text);
+        let text = format!(
+            "{} {}{}{} {}/{}",
+            config.vps_name,
+            quota_title,
+            label,
+            condition_text,
+            usage_text,
+            limit_text
+        );
+        let context = ActionContext {
+            usage: Some(Decimal::from(traffic_usage)),
+            limit: Some(Decimal::from(traffic_limit)),
+            message: Some(text),
+            ..cycle_context.clone()
+        };
+        if let Err(e) = finish_notify(app_state, context, notify, &mut action_list).await {
+            tracing::error!("保存阈值触发记录失败，下次采集时重试: {:?}", e);
         }
     }
-    *app_state.cycle.write().await = Some(cycle);
-    return anyhow::Ok(());
+    action_svc::spawn(app_state, action_list);
 }
 
-async fn generate_cycle(app_state: &AppState) -> anyhow::Result<()> {
-    let config = &app_state.config;
-    if config.traffic_cycle.is_none() {
-        tracing::info!("config[traffic_cycle] 没有配置，不生成流量周期");
+/// 阈值条件的显示，例如: 超80%  剩余不足20.00 GB  超70% 周期剩余5天内
+fn notify_condition_show(notify: &CycleNotifyAppState) -> String {
+    let mut condition_list = vec![];
+    if notify.percent > Decimal::ZERO {
+        condition_list.push(format!("超{}%", notify.percent.normalize()));
+    }
+    if let Some(used) = notify.used {
+        condition_list.push(format!("已用超{}", traffic_show(used)));
+    }
+    if let Some(remaining) = notify.remaining {
+        condition_list.push(format!("剩余不足{}", traffic_show(remaining)));
+    }
+    if let Some(remaining_days) = notify.remaining_days {
+        // 只有剩余天数条件时与前面的方向名称隔开
+        let separator = if condition_list.is_empty() { " " } else { "" };
+        condition_list.push(format!("{}周期剩余{}天内", separator, remaining_days));
+    }
+    condition_list.join(" ")
+}
+
+/// 阈值条件的唯一标识，只有百分比时为 percent:80，与没有 condition 的历史记录兼容
+fn notify_condition(notify: &CycleNotifyAppState) -> String {
+    let mut condition_list = vec![];
+    if let Some(cost) = notify.cost {
+        condition_list.push(format!("cost:{}", cost.normalize()));
+    }
+    if notify.percent > Decimal::ZERO {
+        condition_list.push(format!("percent:{}", notify.percent.normalize()));
+    }
+    if let Some(used) = notify.used {
+        condition_list.push(format!("used:{}", used));
+    }
+    if let Some(remaining) = notify.remaining {
+        condition_list.push(format!("remaining:{}", remaining));
+    }
+    if let Some(remaining_days) = notify.remaining_days {
+        condition_list.push(format!("remaining_days:{}", remaining_days));
+    }
+    condition_list.join(",")
+}
+
+/// 阈值触发后先保存触发记录并标记为已触发，再发送通知并把配置的动作加入 action_list 等待执行
+/// 动作可能是关机这样会结束进程的命令，记录保存失败时不执行，避免重启后重复触发
+async fn finish_notify(
+    app_state: &AppState,
+    mut context: ActionContext,
+    notify: &mut CycleNotifyAppState,
+    action_list: &mut Vec<(ActionContext, Vec<ActionConfig>)>,
+) -> anyhow::Result<()> {
+    let monitor_notify = MonitorNotify {
+        id: None,
+        create_time: None,
+        quota_name: Some(context.quota_name.clone()),
+        cycle_start_date: Some(context.cycle_start_date),
+        cycle_end_date: Some(context.cycle_end_date),
+        percent: Some(notify.percent.trunc().to_u8().unwrap_or(u8::MAX)),
+        target: Some(notify_target_name(&notify.target).to_string()),
+        cost: notify.cost.map(|cost| cost.normalize().to_string()),
+        day: None,
+        condition: Some(notify.condition.clone()),
+    };
+    monitor_notify_mapper::create(monitor_notify, &app_state.db_pool).await?;
+    notify.finished = true;
+    if let Some(text) = &context.message {
+        tg_util::send_msg(&app_state.config, text.clone()).await;
+    }
+    context.target = Some(notify_target_name(&notify.target).to_string());
+    context.condition = Some(notify.condition.clone());
+    action_list.push((context, notify.exec.clone()));
+    anyhow::Ok(())
+}
+
+/// 周期切换时对上一周期执行 on_cycle_end，对当前周期执行 on_cycle_start，保存记录后在后台依次执行，已执行过的不会重复执行
+async fn run_cycle_hooks(app_state: &AppState, liftcycle: &TrafficCycleConfig, cycle: &CycleAppState) -> anyhow::Result<()> {
+    let pre_start = match time_util::previous_cycle_start_date(&cycle.cycle_type, cycle.current_cycle_start_date) {
+        Some(pre_start) => pre_start,
+        None => return anyhow::Ok(()),
+    };
+    let pre_end = cycle.current_cycle_start_date - Duration::days(1);
+    let mut hook_list = vec![];
+    for (actions, target, cycle_start_date, cycle_end_date) in [
+        (&liftcycle.on_cycle_end, "cycle_end", pre_start, pre_end),
+        (&liftcycle.on_cycle_start, "cycle_start", cycle.current_cycle_start_date, cycle.current_cycle_end_date),
+    ] {
+        let actions = match actions {
+            Some(actions) if !actions.is_empty() => actions,
+            _ => continue,
+        };
+        let executed = monitor_notify_mapper::list_cycle_data(&cycle.name, cycle_start_date, cycle_end_date, &app_state.db_pool)
+            .await?
+            .iter()
+            .any(|ele| ele.target.as_deref() == Some(target));
+        if executed {
+            continue;
+        }
+        let context = ActionContext {
+            event: target.to_string(),
+            quota_name: cycle.name.clone(),
+            cycle_start_date,
+            cycle_end_date,
+            ..Default::default()
+        };
+        hook_list.push((context, actions.clone()));
+        let monitor_notify = MonitorNotify {
+            id: None,
+            create_time: None,
+            quota_name: Some(cycle.name.clone()),
+            cycle_start_date: Some(cycle_start_date),
+            cycle_end_date: Some(cycle_end_date),
+            percent: Some(0),
+            target: Some(target.to_string()),
+            cost: None,
+            day: None,
+            condition: None,
+        };
+        monitor_notify_mapper::create(monitor_notify, &app_state.db_pool).await?;
+    }
+    action_svc::spawn(app_state, hook_list);
+    anyhow::Ok(())
+}
+
+/// 启动时处于应用停止期间开始的新周期时补执行周期切换的命令
+/// 只按停止前最后一次采样的时间判断，最后一次采样早于当前周期时才执行，没有采样(首次使用)或在周期中途重启时不执行，避免撤销本周期的限制
+async fn resume_cycle_hooks(
+    app_state: &AppState,
+    liftcycle: &TrafficCycleConfig,
+    cycle: &CycleAppState,
+    last_sample_time: Option<NaiveDateTime>,
+) -> anyhow::Result<()> {
+    if liftcycle.on_cycle_start.is_none() && liftcycle.on_cycle_end.is_none() {
+        return anyhow::Ok(());
+    }
+    if !cycle_hooks_missed(last_sample_time, cycle.current_cycle_start_date, cycle.reset_hour, app_state.config.tz()) {
         return anyhow::Ok(());
     }
-    let liftcycle = config.traffic_cycle.as_ref().unwrap();
+    tracing::info!("流量周期 {} 在应用停止期间进入新周期，补执行周期切换命令", cycle.name);
+    run_cycle_hooks(app_state, liftcycle, cycle).await
+}
+
+fn cycle_hooks_missed(last_sample_time: Option<NaiveDateTime>, current_cycle_start_date: NaiveDate, reset_hour: Option<u32>, tz: Tz) -> bool {
+    last_sample_time.is_some_and(|time| time_util::cycle_day(time, reset_hour, tz) < current_cycle_start_date)
+}
+
+/// 流量周期中各网卡最后一次采样的结束时间
+async fn last_sample_time(app_state: &AppState, liftcycle: &TrafficCycleConfig) -> anyhow::Result<Option<NaiveDateTime>> {
+    let network_name_list = liftcycle.network_name.clone().unwrap_or_else(|| app_state.config.network_name.clone());
+    let mut last_sample_time = None;
+    for network_name in &network_name_list {
+        if let Some(pre_data) = monitor_second_mapper::get_pre_data(network_name, &app_state.db_pool).await? {
+            last_sample_time = std::cmp::max(last_sample_time, pre_data.end_time);
+        }
+    }
+    anyhow::Ok(last_sample_time)
+}
+
+async fn generate_cycle(app_state: &AppState) -> anyhow::Result<()> {
+    let cycle_config_list = match &app_state.config.traffic_cycle {
+        Some(cycle_config_list) => cycle_config_list,
+        None => {
+            tracing::info!("config[traffic_cycle] 没有配置，不生成流量周期");
+            return anyhow::Ok(());
+        }
+    };
+    let mut cycle_list = vec![];
+    for cycle_config in cycle_config_list {
+        // 生成周期时会补写上一周期的历史，需要在此之前读取停止前最后一次采样的时间
+        let last_sample_time = last_sample_time(app_state, cycle_config).await?;
+        let cycle = build_cycle(app_state, cycle_config).await?;
+        resume_cycle_hooks(app_state, cycle_config, &cycle, last_sample_time).await?;
+        cycle_list.push(cycle);
+    }
+    *app_state.cycles.write().await = cycle_list;
+    anyhow::Ok(())
+}
+
+/// 按配置生成当前所处的流量周期，并统计周期内已用的流量
+async fn build_cycle(app_state: &AppState, liftcycle: &TrafficCycleConfig) -> anyhow::Result<CycleAppState> {
+    let config = &app_state.config;
+    let parse_date = |date: &Option<String>, name: &str| -> anyhow::Result<NaiveDate> {
+        match date {
+            Some(date) => anyhow::Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?),
+            None => Err(anyhow!("config[liftcycle][{}] 没有配置", name)),
+        }
+    };
+    let each = match (liftcycle.cycle_type.as_str(), liftcycle.each) {
+        ("once", _) => 0,
+        (_, Some(each)) if each > 0 => each,
+        _ => return Err(anyhow!("config[liftcycle][each] 配置填写错误，每多少个周期重置流量，必须是一个大于0的数")),
+    };
     let cycle_type = match liftcycle.cycle_type.as_str() {
-        "day" => CycleType::DAY(
-            liftcycle.each.unwrap(),
-            chrono::NaiveDate::parse_from_str(
-                liftcycle.traffic_reset_date.as_ref().unwrap(),
-                "%Y-%m-%d",
-            )?,
-        ),
-        "month" => CycleType::MONTH(
-            liftcycle.each.unwrap(),
-            chrono::NaiveDate::parse_from_str(
-                liftcycle.traffic_reset_date.as_ref().unwrap(),
-                "%Y-%m-%d",
-            )?,
-        ),
+        "day" => CycleType::DAY(each, parse_date(&liftcycle.traffic_reset_date, "traffic_reset_date")?),
+        "week" => CycleType::WEEK(each, parse_week_reset_date(liftcycle)?),
+        "month" => {
+            let traffic_reset_date = parse_date(&liftcycle.traffic_reset_date, "traffic_reset_date")?;
+            CycleType::MONTH(each, traffic_reset_date, parse_reset_day(&liftcycle.reset_day, traffic_reset_date)?)
+        }
+        "year" => {
+            let traffic_reset_date = parse_date(&liftcycle.traffic_reset_date, "traffic_reset_date")?;
+            CycleType::YEAR(each, traffic_reset_date, parse_reset_day(&liftcycle.reset_day, traffic_reset_date)?)
+        }
         "once" => CycleType::ONCE(
-            chrono::NaiveDate::parse_from_str(liftcycle.start_date.as_ref().unwrap(), "%Y-%m-%d")?,
-            chrono::NaiveDate::parse_from_str(liftcycle.end_date.as_ref().unwrap(), "%Y-%m-%d")?,
+            parse_date(&liftcycle.start_date, "start_date")?,
+            parse_date(&liftcycle.end_date, "end_date")?,
         ),
         _ => {
             return Err(anyhow!(
@@ -420,126 +1145,545 @@ async fn generate_cycle(app_state: &AppState) -> anyhow::Result<()> {
             ))
         }
     };
-    let now = chrono::Local::now().date_naive();
-    let (mut current_cycle_start_date, mut current_cycle_end_date);
-    if let CycleType::ONCE(start, end) = cycle_type {
-        current_cycle_start_date = start;
-        current_cycle_end_date = end;
-    } else {
-        let (each, mut traffic_reset_date) = match cycle_type {
-            CycleType::DAY(each, traffic_reset_date) => (each, traffic_reset_date),
-            CycleType::MONTH(each, traffic_reset_date) => (each, traffic_reset_date),
-            _ => return Err(anyhow!("cycle_type 不会出现此类型")),
-        };
-        if each <= 0 {
-            return Err(anyhow!("config[liftcycle][each] 配置填写错误，每多少天或每多少个月重置流量，必须是一个大于0的数"));
-        }
-        let add_or_sub = if now >= traffic_reset_date { 1 } else { -1 };
-        loop {
-            let end = match cycle_type {
-                CycleType::DAY(each, _) => {
-                    traffic_reset_date + chrono::Duration::days(each * add_or_sub)
-                }
-                CycleType::MONTH(each, _) => {
-                    if add_or_sub == 1 {
-                        traffic_reset_date
-                            .checked_add_months(Months::new(each as u32))
-                            .unwrap()
-                    } else {
-                        traffic_reset_date
-                            .checked_sub_months(Months::new(each as u32))
-                            .unwrap()
-                    }
-                }
-                _ => return Err(anyhow!("cycle_type 不会出现此类型")),
-            };
-            current_cycle_start_date = std::cmp::min(traffic_reset_date, end);
-            current_cycle_end_date =
-                std::cmp::max(traffic_reset_date, end) - chrono::Duration::days(1);
-            if now >= current_cycle_start_date && now <= current_cycle_end_date {
-                break;
-            }
-            traffic_reset_date = end;
-        }
+    if liftcycle.reset_hour.is_some_and(|reset_hour| reset_hour > 23) {
+        return Err(anyhow!("config[liftcycle][reset_hour] 配置填写错误，只能填写 0 ~ 23"));
     }
-    let statistic_method = match liftcycle.statistic_method.as_str() {
-        "sum(in,out)" => CycleStatisticMethod::SumInOut,
-        "max(in,out)" => CycleStatisticMethod::MaxInOut,
-        "out" => CycleStatisticMethod::OnlyOut,
-        _ => {
+    let now = time_util::cycle_today(liftcycle.reset_hour, config.tz());
+    let (current_cycle_start_date, current_cycle_end_date) = time_util::cycle_range(&cycle_type, now);
+    let statistic_method = CycleStatisticMethod::try_from(liftcycle.statistic_method.clone())
+        .map_err(|e| anyhow!("config[liftcycle][statistic_method] 配置填写错误: {}", e))?;
+    let traffic_limit = parse_traffic_limit(&liftcycle.traffic_limit, "traffic_limit")?;
+    let uplink_limit = parse_traffic_limit(&liftcycle.uplink_limit, "uplink_limit")?;
+    let downlink_limit = parse_traffic_limit(&liftcycle.downlink_limit, "downlink_limit")?;
+    let billing_mode = match liftcycle.billing_mode.as_str() {
+        "volume" => CycleBillingMode::Volume,
+        "p95" => CycleBillingMode::Percentile95,
+        _ => return Err(anyhow!("config[liftcycle][billing_mode] 配置填写错误，没有这样的类型")),
+    };
+    let committed_rate = parse_rate(&liftcycle.committed_rate, "committed_rate")?;
+    match billing_mode {
+        CycleBillingMode::Volume if traffic_limit.is_none() && uplink_limit.is_none() && downlink_limit.is_none() => {
             return Err(anyhow!(
-                "config[liftcycle][statistic_method] 配置填写错误，没有这样的类型"
-            ))
+                "config[liftcycle] traffic_limit uplink_limit downlink_limit 至少需要配置一个"
+            ));
         }
-    };
-    let traffic_limit = &liftcycle
-        .traffic_limit
-        .replace(" ", "")
-        .replace(",", "")
-        .replace("_", "");
-    let traffic_limit = if let Some(traffic_limit) = traffic_limit.strip_suffix("MB") {
-        Decimal::from_str(traffic_limit).unwrap() * Decimal::from_i64(MB).unwrap()
-    } else if let Some(traffic_limit) = traffic_limit.strip_suffix("GB") {
-        Decimal::from_str(traffic_limit).unwrap() * Decimal::from_i64(GB).unwrap()
-    } else if let Some(traffic_limit) = traffic_limit.strip_suffix("TB") {
-        Decimal::from_str(traffic_limit).unwrap() * Decimal::from_i64(TB).unwrap()
-    } else {
+        CycleBillingMode::Percentile95 if committed_rate.is_none() => {
+            return Err(anyhow!("config[liftcycle] billing_mode 为 p95 时 committed_rate 必须配置"));
+        }
+        _ => {}
+    }
+    let network_name = liftcycle.network_name.clone().unwrap_or(config.network_name.clone());
+    if let Some(ele) = network_name.iter().find(|ele| !config.network_name.contains(ele)) {
         return Err(anyhow!(
-            "config[liftcycle][traffic_limit] 需要以 MB GB TB 结尾"
+            "config[liftcycle][network_name] 配置填写错误，网卡 {} 不在 config[network_name] 中",
+            ele
         ));
+    }
+    let free_windows = parse_free_windows(&liftcycle.free_windows)?;
+    validate_actions(&liftcycle.on_cycle_start, "on_cycle_start")?;
+    validate_actions(&liftcycle.on_cycle_end, "on_cycle_end")?;
+    let pacing = match &liftcycle.pacing {
+        Some(_) if billing_mode != CycleBillingMode::Volume || traffic_limit.is_none() => {
+            return Err(anyhow!("config[liftcycle][pacing] 只支持 billing_mode 为 volume，并且需要配置 traffic_limit"));
+        }
+        Some(pacing) if pacing.multiple <= Decimal::ZERO => {
+            return Err(anyhow!("config[liftcycle][pacing][multiple] 必须是一个大于0的数"));
+        }
+        Some(pacing) => Some(CyclePacing {
+            multiple: pacing.multiple,
+            exec: validate_actions(&pacing.exec, "pacing][exec")?,
+            end_exec: validate_actions(&pacing.end_exec, "pacing][end_exec")?,
+            allowance: None,
+            triggered_day: cycle_svc::pacing_triggered_day(app_state, &liftcycle.name).await?,
+        }),
+        None => None,
     };
-    let traffic_limit = traffic_limit.to_string().parse::<i64>()?;
-    let now = chrono::Local::now().date_naive();
-    let day_start_time = now.and_hms_opt(0, 0, 0).unwrap();
-    let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage) =
-        monitor_day_mapper::sum_daterange_data(
-            current_cycle_start_date,
-            chrono::Local::now().date_naive() - chrono::Duration::days(1),
-            &app_state.db_pool,
-        )
-        .await?
-        .unwrap_or((0, 0));
-    let (today_uplink_traffic_usage, today_downlink_traffic_usage) =
-        monitor_second_mapper::sum_timerange_data(
-            day_start_time,
-            day_start_time + Duration::days(1),
-            &app_state.db_pool,
-        )
-        .await?
-        .unwrap_or((0, 0));
-    let uplink_traffic_usage = cycle_day_uplink_traffic_usage + today_uplink_traffic_usage;
-    let downlink_traffic_usage = cycle_day_downlink_traffic_usage + today_downlink_traffic_usage;
-    let traffic_usage = match statistic_method {
-        CycleStatisticMethod::MaxInOut => {
-            std::cmp::max(uplink_traffic_usage, downlink_traffic_usage)
-        }
-        CycleStatisticMethod::OnlyOut => uplink_traffic_usage,
-        CycleStatisticMethod::SumInOut => uplink_traffic_usage + downlink_traffic_usage,
-    };
+    let (uplink_traffic_usage, downlink_traffic_usage, free_uplink_traffic_usage, free_downlink_traffic_usage) =
+        if free_windows.is_empty() && liftcycle.reset_hour.is_none() {
+            let (cycle_day_uplink_traffic_usage, cycle_day_downlink_traffic_usage) =
+                monitor_day_mapper::sum_daterange_data(
+                    &network_name,
+                    current_cycle_start_date,
+                    now - chrono::Duration::days(1),
+                    &app_state.db_pool,
+                )
+                .await?
+                .unwrap_or((0, 0));
+            let (today_uplink_traffic_usage, today_downlink_traffic_usage) =
+                monitor_second_mapper::sum_timerange_data(
+                    &network_name,
+                    time_util::day_start(now, config.tz()),
+                    time_util::day_start(now + Duration::days(1), config.tz()),
+                    &app_state.db_pool,
+                )
+                .await?
+                .unwrap_or((0, 0));
+            (
+                cycle_day_uplink_traffic_usage + today_uplink_traffic_usage,
+                cycle_day_downlink_traffic_usage + today_downlink_traffic_usage,
+                0,
+                0,
+            )
+        } else {
+            // 小时数据随每次采集更新，已包含今天的流量
+            cycle_svc::sum_cycle_traffic(app_state, &network_name, &free_windows, liftcycle.reset_hour, current_cycle_start_date, now).await?
+        };
+    let traffic_usage = statistic_method.traffic_usage(uplink_traffic_usage, downlink_traffic_usage);
+    let finished_notify_list: Vec<(String, String)> = monitor_notify_mapper::list_cycle_data(
+        &liftcycle.name,
+        current_cycle_start_date,
+        current_cycle_end_date,
+        &app_state.db_pool,
+    )
+    .await?
+    .into_iter()
+    .filter_map(|ele| {
+        // 没有 condition 的历史记录只有百分比或超额费用
+        let condition = match (ele.condition, ele.cost) {
+            (Some(condition), _) => condition,
+            (None, Some(cost)) => format!("cost:{}", cost),
+            (None, None) => format!("percent:{}", ele.percent?),
+        };
+        Some((ele.target?, condition))
+    })
+    .collect();
     let mut cycle_notify_list = vec![];
     if let Some(notify) = &liftcycle.notify {
         let mut notify = notify.clone();
-        notify.sort_by(|a, b| b.percent.cmp(&a.percent));
+        notify.sort_by_key(|ele| (ele.percent, ele.cost));
         for ele in notify {
-            cycle_notify_list.push(CycleNotifyAppState {
-                percent: ele.percent,
-                exec: ele.exec,
+            let (target, limit) = match ele.target.as_str() {
+                // 按 95 计费带宽计费时阈值以承诺带宽为基数
+                "total" if billing_mode == CycleBillingMode::Percentile95 => (CycleNotifyTarget::Total, committed_rate),
+                _ if billing_mode == CycleBillingMode::Percentile95 => {
+                    return Err(anyhow!(
+                        "config[liftcycle][notify][target] billing_mode 为 p95 时只能为 total"
+                    ))
+                }
+                "total" => (CycleNotifyTarget::Total, traffic_limit),
+                "uplink" => (CycleNotifyTarget::Uplink, uplink_limit),
+                "downlink" => (CycleNotifyTarget::Downlink, downlink_limit),
+                "cost" => (CycleNotifyTarget::Cost, None),
+                "forecast" => (CycleNotifyTarget::Forecast, traffic_limit),
+                _ => {
+                    return Err(anyhow!(
+                        "config[liftcycle][notify][target] 配置填写错误，没有这样的类型"
+                    ))
+                }
+            };
+            match target {
+                CycleNotifyTarget::Cost if liftcycle.pricing.is_none() || ele.cost.is_none() => {
+                    return Err(anyhow!(
+                        "config[liftcycle][notify] 统计方向为 cost 时需要配置 cost 和 config[liftcycle][pricing]"
+                    ));
+                }
+                CycleNotifyTarget::Cost => {}
+                _ if limit.is_none() => {
+                    return Err(anyhow!(
+                        "config[liftcycle][notify] 阈值 {}% 的统计方向 {} 没有配置对应的流量限制",
+                        ele.percent,
+                        ele.target
+                    ));
+                }
+                _ if ele.percent < Decimal::ZERO => {
+                    return Err(anyhow!("config[liftcycle][notify][percent] 不能小于 0"));
+                }
+                CycleNotifyTarget::Forecast if ele.percent == Decimal::ZERO => {
+                    return Err(anyhow!("config[liftcycle][notify][percent] 统计方向为 forecast 时必须是一个大于0的数"));
+                }
+                _ if ele.percent == Decimal::ZERO && ele.used.is_none() && ele.remaining.is_none() && ele.remaining_days.is_none() => {
+                    return Err(anyhow!(
+                        "config[liftcycle][notify] percent used remaining remaining_days 至少需要配置一个"
+                    ));
+                }
+                _ => {}
+            }
+            let used = parse_traffic_limit(&ele.used, "notify][used")?;
+            let remaining = parse_traffic_limit(&ele.remaining, "notify][remaining")?;
+            let amount_supported = billing_mode == CycleBillingMode::Volume
+                && matches!(target, CycleNotifyTarget::Total | CycleNotifyTarget::Uplink | CycleNotifyTarget::Downlink);
+            if (used.is_some() || remaining.is_some()) && !amount_supported {
+                return Err(anyhow!(
+                    "config[liftcycle][notify] used remaining 只支持 billing_mode 为 volume 且统计方向为 total uplink downlink"
+                ));
+            }
+            let is_cost = target == CycleNotifyTarget::Cost;
+            let mut cycle_notify = CycleNotifyAppState {
+                percent: if is_cost { Decimal::ZERO } else { ele.percent },
+                used,
+                remaining,
+                remaining_days: ele.remaining_days.map(|ele| ele as i64),
+                target,
+                cost: ele.cost.filter(|_| is_cost),
+                condition: String::new(),
                 finished: false,
-            });
+                exec: validate_actions(&ele.exec, "notify][exec")?,
+            };
+            cycle_notify.condition = notify_condition(&cycle_notify);
+            cycle_notify.finished = finished_notify_list.contains(&(ele.target.clone(), cycle_notify.condition.clone()));
+            cycle_notify_list.push(cycle_notify);
         }
     }
-    let cycle = CycleAppState {
+    let mut cycle = CycleAppState {
+        name: liftcycle.name.clone(),
         cycle_type,
         current_cycle_start_date,
         current_cycle_end_date,
+        reset_hour: liftcycle.reset_hour,
+        network_name,
         uplink_traffic_usage,
         downlink_traffic_usage,
         traffic_limit,
+        uplink_limit,
+        downlink_limit,
+        traffic_rollover: None,
+        uplink_rollover: None,
+        downlink_rollover: None,
+        billing_mode,
+        committed_rate,
+        rate_p95: None,
+        rate_p95_time: None,
+        pricing: None,
+        overage_cost: None,
+        forecast: None,
+        projected_traffic_usage: None,
+        projected_exceed_date: None,
+        pacing,
         traffic_usage,
+        free_uplink_traffic_usage,
+        free_downlink_traffic_usage,
         notify: cycle_notify_list,
         statistic_method,
+        free_windows,
     };
+    // 先保存上一周期的历史，结转流量按上一周期历史中的实际限制和用量计算
+    if let Err(e) = cycle_svc::archive_previous_cycle(app_state, liftcycle, &cycle).await {
+        tracing::error!("保存流量周期 {} 上一周期历史失败: {:?}", liftcycle.name, e);
+    }
+    (cycle.traffic_rollover, cycle.uplink_rollover, cycle.downlink_rollover) =
+        cycle_svc::cycle_rollover(app_state, liftcycle, &cycle, cycle.current_cycle_start_date).await?;
+    cycle.traffic_limit = cycle.traffic_limit.map(|limit| limit + cycle.traffic_rollover.unwrap_or(0));
+    cycle.uplink_limit = cycle.uplink_limit.map(|limit| limit + cycle.uplink_rollover.unwrap_or(0));
+    cycle.downlink_limit = cycle.downlink_limit.map(|limit| limit + cycle.downlink_rollover.unwrap_or(0));
+    // 超额计费的免费额度默认为包含结转流量的 traffic_limit
+    cycle.pricing = cycle_svc::parse_pricing(&liftcycle.pricing, &cycle.billing_mode, cycle.traffic_limit)?;
+    cycle.overage_cost = cycle.pricing.as_ref().map(|pricing| pricing.overage_cost(cycle.traffic_usage));
+    cycle_svc::update_forecast(app_state, &mut cycle).await?;
+    if let (Some(pacing), Some(forecast)) = (&mut cycle.pacing, &cycle.forecast) {
+        pacing.allowance = cycle_svc::daily_allowance(cycle.traffic_limit, forecast, cycle.current_cycle_end_date);
+    }
+    if cycle.billing_mode == CycleBillingMode::Percentile95 {
+        let rate_p95_time = time_util::truncate_five_minute(time_util::now());
+        cycle.rate_p95 = Some(cycle_svc::cycle_rate_p95(app_state, &cycle, cycle.current_cycle_start_date, rate_p95_time).await?);
+        cycle.rate_p95_time = Some(rate_p95_time);
+    }
     tracing::info!("流量周期: {:#?}", &cycle);
-    *app_state.cycle.write().await = Some(cycle);
-    anyhow::Ok(())
+    anyhow::Ok(cycle)
+}
+
+fn notify_target_name(target: &CycleNotifyTarget) -> &'static str {
+    match target {
+        CycleNotifyTarget::Total => "total",
+        CycleNotifyTarget::Uplink => "uplink",
+        CycleNotifyTarget::Downlink => "downlink",
+        CycleNotifyTarget::Cost => "cost",
+        CycleNotifyTarget::Forecast => "forecast",
+    }
+}
+
+/// 解析 100Mbps 1Gbps 这样的带宽，单位 bit/s，没有配置时为空
+fn parse_rate(rate: &Option<String>, name: &str) -> anyhow::Result<Option<i64>> {
+    let rate = match rate {
+        Some(rate) => rate.replace(" ", "").replace(",", "").replace("_", ""),
+        None => return anyhow::Ok(None),
+    };
+    let rate = if let Some(rate) = rate.strip_suffix("Kbps") {
+        Decimal::from_str(rate)? * dec!(1000)
+    } else if let Some(rate) = rate.strip_suffix("Mbps") {
+        Decimal::from_str(rate)? * dec!(1000000)
+    } else if let Some(rate) = rate.strip_suffix("Gbps") {
+        Decimal::from_str(rate)? * dec!(1000000000)
+    } else {
+        return Err(anyhow!(
+            "config[liftcycle][{}] 需要以 Kbps Mbps Gbps 结尾",
+            name
+        ));
+    };
+    anyhow::Ok(Some(rate.trunc().to_string().parse::<i64>()?))
+}
+
+/// 校验动作配置，没有配置时为空
+fn validate_actions(actions: &Option<Vec<ActionConfig>>, name: &str) -> anyhow::Result<Vec<ActionConfig>> {
+    let actions = actions.clone().unwrap_or_default();
+    action_svc::validate(&actions, name)?;
+    anyhow::Ok(actions)
+}
+
+/// 解析免费时段，start end 只能为整点，不填 weekday 时每天生效
+fn parse_free_windows(free_windows: &Option<Vec<FreeWindowConfig>>) -> anyhow::Result<Vec<FreeWindow>> {
+    let parse_hour = |time: &str, name: &str| -> anyhow::Result<u32> {
+        match time.split_once(':') {
+            Some((hour, "00")) if hour.parse::<u32>().is_ok_and(|hour| hour <= 24) => anyhow::Ok(hour.parse::<u32>()?),
+            _ => Err(anyhow!(
+                "config[liftcycle][free_windows][{}] 配置填写错误，免费时段按小时统计，只能填写 00:00 ~ 24:00 的整点",
+                name
+            )),
+        }
+    };
+    let mut free_window_list = vec![];
+    for free_window in free_windows.iter().flatten() {
+        let start_hour = parse_hour(&free_window.start, "start")?;
+        let end_hour = parse_hour(&free_window.end, "end")?;
+        if start_hour == 24 || start_hour == end_hour {
+            return Err(anyhow!(
+                "config[liftcycle][free_windows] 配置填写错误，start 不能为 24:00，且 start end 不能相同"
+            ));
+        }
+        let weekday = match &free_window.weekday {
+            Some(weekday) => weekday
+                .iter()
+                .map(|ele| {
+                    ele.parse::<Weekday>()
+                        .map_err(|_| anyhow!("config[liftcycle][free_windows][weekday] 配置填写错误，没有这样的星期: {}", ele))
+                })
+                .collect::<anyhow::Result<Vec<Weekday>>>()?,
+            None => vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun],
+        };
+        free_window_list.push(FreeWindow {
+            weekday,
+            start_hour,
+            end_hour: end_hour % 24,
+        });
+    }
+    anyhow::Ok(free_window_list)
+}
+
+/// 配置了免费时段时显示免费时段的流量
+fn free_show(cycle: &CycleAppState, free_uplink_traffic_usage: i64, free_downlink_traffic_usage: i64) -> String {
+    if cycle.free_windows.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n免费时段 上传: {} 下载: {}",
+        traffic_show(free_uplink_traffic_usage),
+        traffic_show(free_downlink_traffic_usage)
+    )
+}
+
+/// 配置了结转时显示本周期结转的流量，已包含在限制中
+fn rollover_show(cycle: &CycleAppState) -> String {
+    let rollover_list = [
+        ("计入流量", cycle.traffic_rollover),
+        ("上行", cycle.uplink_rollover),
+        ("下行", cycle.downlink_rollover),
+    ]
+    .iter()
+    .filter_map(|(label, rollover)| rollover.map(|rollover| format!("{} {}", label, traffic_show(rollover))))
+    .collect::<Vec<String>>();
+    if rollover_list.is_empty() {
+        return String::new();
+    }
+    format!("\n上期结转: {}", rollover_list.join(" "))
+}
+
+/// 解析每月重置日，没有配置时为重置日期的日
+fn parse_reset_day(reset_day: &Option<ResetDayConfig>, traffic_reset_date: NaiveDate) -> anyhow::Result<MonthDay> {
+    let month_day = match reset_day {
+        None => MonthDay::Day(traffic_reset_date.day()),
+        Some(ResetDayConfig::Rule(rule)) if rule == "last" => MonthDay::Last,
+        Some(ResetDayConfig::Rule(rule)) => MonthDay::Day(rule.parse::<u32>().unwrap_or(0)),
+        Some(ResetDayConfig::Day(day)) => MonthDay::Day(*day),
+    };
+    if let MonthDay::Day(day) = month_day {
+        if !(1..=31).contains(&day) {
+            return Err(anyhow!("config[liftcycle][reset_day] 配置填写错误，只能填写 1 ~ 31 或 last"));
+        }
+    }
+    anyhow::Ok(month_day)
+}
+
+/// 周周期的重置日期，没有配置 traffic_reset_date 时按 reset_weekday 取一个该星期的日期
+/// 两者都配置时 traffic_reset_date 必须是 reset_weekday 对应的星期
+fn parse_week_reset_date(liftcycle: &TrafficCycleConfig) -> anyhow::Result<NaiveDate> {
+    let weekday = match &liftcycle.reset_weekday {
+        Some(weekday) => Some(
+            weekday
+                .parse::<Weekday>()
+                .map_err(|_| anyhow!("config[liftcycle][reset_weekday] 配置填写错误，没有这样的星期: {}", weekday))?,
+        ),
+        None => None,
+    };
+    let weekday = match (&liftcycle.traffic_reset_date, weekday) {
+        (Some(traffic_reset_date), weekday) => {
+            let traffic_reset_date = NaiveDate::parse_from_str(traffic_reset_date, "%Y-%m-%d")?;
+            if weekday.is_some_and(|weekday| weekday != traffic_reset_date.weekday()) {
+                return Err(anyhow!(
+                    "config[liftcycle][reset_weekday] 配置填写错误，与 traffic_reset_date {} 的星期 {} 不一致",
+                    traffic_reset_date,
+                    traffic_reset_date.weekday()
+                ));
+            }
+            return anyhow::Ok(traffic_reset_date);
+        }
+        (None, Some(weekday)) => weekday,
+        (None, None) => return Err(anyhow!("config[liftcycle] 周期为 week 时 traffic_reset_date reset_weekday 至少需要配置一个")),
+    };
+    // 2024-01-01 为星期一
+    anyhow::Ok(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + Duration::days(weekday.num_days_from_monday() as i64))
+}
+
+#[cfg(test)]
+mod statistics_svc_test {
+    use crate::util::traffic_util::MB;
+
+    use super::*;
+
+    #[test]
+    fn counter_usage_test() {
+        assert_eq!(counter_usage(100, 150, false, true), (50, None));
+        assert_eq!(counter_usage(100, 150, true, false), (150, None));
+        assert_eq!(counter_usage(u32::MAX as i64 - 9, 20, false, true), (30, Some("计数器回绕")));
+        assert_eq!(counter_usage(u32::MAX as i64 - 9, 20, false, false), (20, Some("计数器重置")));
+        assert_eq!(counter_usage(u32::MAX as i64 + 100, 20, false, true), (20, Some("计数器重置")));
+        // 同一次启动中 64 位计数器从较小的读数清零，不能按回绕记为约 4GB 的用量
+        assert_eq!(counter_usage(100 * MB, 0, false, true), (0, Some("计数器重置")));
+        assert_eq!(counter_usage(u32::MAX as i64 + 1 - GB - 1, 0, false, true), (0, Some("计数器重置")));
+        assert_eq!(counter_usage(u32::MAX as i64 + 1 - GB, 0, false, true), (GB, Some("计数器回绕")));
+    }
+
+    #[test]
+    fn parse_rate_test() {
+        assert_eq!(parse_rate(&Some("100Mbps".to_string()), "committed_rate").unwrap(), Some(100_000_000));
+        assert_eq!(parse_rate(&Some("1.5 Gbps".to_string()), "committed_rate").unwrap(), Some(1_500_000_000));
+        assert!(parse_rate(&Some("100MB".to_string()), "committed_rate").is_err());
+    }
+
+    #[test]
+    fn parse_free_windows_test() {
+        let free_window_list = parse_free_windows(&Some(vec![
+            FreeWindowConfig { weekday: None, start: "00:00".to_string(), end: "08:00".to_string() },
+            FreeWindowConfig {
+                weekday: Some(vec!["sat".to_string(), "Sunday".to_string()]),
+                start: "00:00".to_string(),
+                end: "24:00".to_string(),
+            },
+            FreeWindowConfig { weekday: Some(vec!["fri".to_string()]), start: "22:00".to_string(), end: "02:00".to_string() },
+        ]))
+        .unwrap();
+        // 不填 weekday 时每天生效，24:00 为零点，00:00 ~ 24:00 为全天
+        assert_eq!(free_window_list[0].weekday.len(), 7);
+        assert_eq!((free_window_list[0].start_hour, free_window_list[0].end_hour), (0, 8));
+        assert_eq!(free_window_list[1].weekday, vec![Weekday::Sat, Weekday::Sun]);
+        assert_eq!((free_window_list[1].start_hour, free_window_list[1].end_hour), (0, 0));
+        assert!(free_window_list[1].contains(Weekday::Sun, 23));
+        assert_eq!((free_window_list[2].start_hour, free_window_list[2].end_hour), (22, 2));
+
+        let invalid_list = [(None, "00:30", "08:00"), (None, "08:00", "08:00"), (None, "24:00", "08:00"), (Some("someday"), "00:00", "08:00")];
+        for (weekday, start, end) in invalid_list {
+            let free_window = FreeWindowConfig {
+                weekday: weekday.map(|ele| vec![ele.to_string()]),
+                start: start.to_string(),
+                end: end.to_string(),
+            };
+            assert!(parse_free_windows(&Some(vec![free_window])).is_err(), "{} {}", start, end);
+        }
+    }
+
+    #[test]
+    fn notify_condition_test() {
+        // 只有百分比时与历史记录的标识相同
+        let notify = CycleNotifyAppState {
+            percent: dec!(80),
+            used: None,
+            remaining: None,
+            remaining_days: None,
+            target: CycleNotifyTarget::Total,
+            cost: None,
+            condition: String::new(),
+            finished: false,
+            exec: vec![],
+        };
+        assert_eq!(notify_condition(&notify), "percent:80");
+        assert_eq!(notify_condition_show(&notify), "超80%");
+
+        let notify = CycleNotifyAppState { percent: dec!(99.50), remaining_days: Some(5), ..notify };
+        assert_eq!(notify_condition(&notify), "percent:99.5,remaining_days:5");
+        assert_eq!(notify_condition_show(&notify), "超99.5% 周期剩余5天内");
+
+        let notify = CycleNotifyAppState { percent: dec!(0), remaining: Some(20 * GB), remaining_days: None, ..notify };
+        assert_eq!(notify_condition(&notify), format!("remaining:{}", 20 * GB));
+
+        let notify = CycleNotifyAppState { remaining: None, target: CycleNotifyTarget::Cost, cost: Some(dec!(5.0)), ..notify };
+        assert_eq!(notify_condition(&notify), "cost:5");
+    }
+
+    #[test]
+    fn cycle_hooks_missed_test() {
+        let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap();
+        let cycle_start_date = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+        let tz: Tz = "Asia/Shanghai".parse().unwrap();
+        // 首次使用没有采样
+        assert!(!cycle_hooks_missed(None, cycle_start_date, None, tz));
+        // 周期中途重启，最后一次采样在当前周期内
+        assert!(!cycle_hooks_missed(Some(time("2024-08-10T12:00:00")), cycle_start_date, None, tz));
+        // UTC 7 月 31 日 16 点之后在东八区已经是 8 月 1 日
+        assert!(!cycle_hooks_missed(Some(time("2024-07-31T16:30:00")), cycle_start_date, None, tz));
+        assert!(cycle_hooks_missed(Some(time("2024-07-31T15:30:00")), cycle_start_date, None, tz));
+        // 配置了重置小时(UTC)时，重置时刻之前仍属于上一周期
+        assert!(cycle_hooks_missed(Some(time("2024-08-01T05:30:00")), cycle_start_date, Some(6), tz));
+        assert!(!cycle_hooks_missed(Some(time("2024-08-01T06:30:00")), cycle_start_date, Some(6), tz));
+    }
+
+    #[test]
+    fn split_gap_test() {
+        let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap();
+        let segment_list = split_by_hour(time("2024-08-05T22:30:00"), time("2024-08-06T01:15:00"), chrono_tz::UTC);
+        assert_eq!(
+            segment_list,
+            vec![
+                (time("2024-08-05T22:30:00"), time("2024-08-05T23:00:00")),
+                (time("2024-08-05T23:00:00"), time("2024-08-06T00:00:00")),
+                (time("2024-08-06T00:00:00"), time("2024-08-06T01:00:00")),
+                (time("2024-08-06T01:00:00"), time("2024-08-06T01:15:00")),
+            ]
+        );
+        assert_eq!(split_usage(1650, &segment_list), vec![300, 600, 600, 150]);
+        assert_eq!(split_usage(1001, &segment_list).iter().sum::<i64>(), 1001);
+
+        // 写入 5 分钟统计前再按 5 分钟切分，中断期间每个 5 分钟都分到用量
+        let segment_list = split_by_five_minute(time("2024-08-05T22:52:30"), time("2024-08-05T23:10:00"));
+        assert_eq!(
+            segment_list,
+            vec![
+                (time("2024-08-05T22:52:30"), time("2024-08-05T22:55:00")),
+                (time("2024-08-05T22:55:00"), time("2024-08-05T23:00:00")),
+                (time("2024-08-05T23:00:00"), time("2024-08-05T23:05:00")),
+                (time("2024-08-05T23:05:00"), time("2024-08-05T23:10:00")),
+            ]
+        );
+        let bucket_list = segment_list
+            .iter()
+            .map(|(segment_start_time, _)| time_util::truncate_five_minute(*segment_start_time))
+            .zip(split_usage(1050, &segment_list))
+            .collect::<Vec<(NaiveDateTime, i64)>>();
+        assert_eq!(
+            bucket_list,
+            vec![
+                (time("2024-08-05T22:50:00"), 150),
+                (time("2024-08-05T22:55:00"), 300),
+                (time("2024-08-05T23:00:00"), 300),
+                (time("2024-08-05T23:05:00"), 300),
+            ]
+        );
+
+        // 半小时时区的整点对应 UTC 的半点
+        let segment_list = split_by_hour(time("2024-08-05T22:00:00"), time("2024-08-05T23:15:00"), "Asia/Kolkata".parse().unwrap());
+        assert_eq!(
+            segment_list,
+            vec![
+                (time("2024-08-05T22:00:00"), time("2024-08-05T22:30:00")),
+                (time("2024-08-05T22:30:00"), time("2024-08-05T23:15:00")),
+            ]
+        );
+    }
 }
@@ -0,0 +1,224 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::app_config::TrafficSourceConfig, service::systemstat_svc, util::file_util};
+
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+const SYS_CLASS_NET_PATH: &str = "/sys/class/net";
+
+/// 网卡计数器读数，均为开机以来的累计值
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TrafficReadings {
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    #[serde(default)]
+    pub tx_packets: u64,
+    #[serde(default)]
+    pub rx_packets: u64,
+    #[serde(default)]
+    pub tx_errors: u64,
+    #[serde(default)]
+    pub rx_errors: u64,
+    #[serde(default)]
+    pub tx_drops: u64,
+    #[serde(default)]
+    pub rx_drops: u64,
+    /// 开机 id，重启后会变化，无法获取时为空
+    #[serde(default)]
+    pub boot_id: Option<String>,
+    /// 网卡序号，网卡删除重建后会变化，无法获取时为空
+    #[serde(default)]
+    pub ifindex: Option<u32>,
+}
+
+fn read_boot_id() -> Option<String> {
+    file_util::read_file(Path::new(BOOT_ID_PATH))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+fn read_ifindex(sys_class_net_path: &Path, network_name: &str) -> Option<u32> {
+    file_util::read_file(&sys_class_net_path.join(network_name).join("ifindex"))
+        .ok()
+        .and_then(|content| content.trim().parse::<u32>().ok())
+}
+
+fn read_sysfs_counter(sys_class_net_path: &Path, network_name: &str, counter: &str) -> anyhow::Result<u64> {
+    let filepath = sys_class_net_path.join(network_name).join("statistics").join(counter);
+    let content = file_util::read_file(&filepath)
+        .map_err(|e| anyhow!("读取 {} 失败: {}", filepath.display(), e))?;
+    anyhow::Ok(content.trim().parse::<u64>()?)
+}
+
+pub trait TrafficSource: Send + Sync {
+    fn read(&self, network_name: &str) -> anyhow::Result<TrafficReadings>;
+}
+
+pub fn init(config: &TrafficSourceConfig) -> anyhow::Result<Arc<dyn TrafficSource>> {
+    let source: Arc<dyn TrafficSource> = match config.source_type.as_str() {
+        "systemstat" => Arc::new(SystemstatSource),
+        "proc_net_dev" => Arc::new(ProcNetDevSource {
+            path: PathBuf::from(config.path.as_deref().unwrap_or("/proc/net/dev")),
+        }),
+        "sysfs" => Arc::new(SysfsSource {
+            path: PathBuf::from(config.path.as_deref().unwrap_or(SYS_CLASS_NET_PATH)),
+        }),
+        "file" => Arc::new(FileSource {
+            path: PathBuf::from(config.path.as_ref().ok_or_else(|| {
+                anyhow!("config[traffic_source][path] 类型为 file 时必须填写文件路径")
+            })?),
+        }),
+        _ => {
+            return Err(anyhow!(
+                "config[traffic_source][source_type] 配置填写错误，没有这样的类型"
+            ))
+        }
+    };
+    tracing::info!("流量数据来源: {}", config.source_type);
+    anyhow::Ok(source)
+}
+
+/// 通过 systemstat 读取，不同平台由 systemstat 自行适配，systemstat 不提供丢包数，尝试从 sysfs 读取
+pub struct SystemstatSource;
+
+impl TrafficSource for SystemstatSource {
+    fn read(&self, network_name: &str) -> anyhow::Result<TrafficReadings> {
+        let network_stats = systemstat_svc::traffic(network_name)?;
+        anyhow::Ok(TrafficReadings {
+            tx_bytes: network_stats.tx_bytes.0,
+            rx_bytes: network_stats.rx_bytes.0,
+            tx_packets: network_stats.tx_packets,
+            rx_packets: network_stats.rx_packets,
+            tx_errors: network_stats.tx_errors,
+            rx_errors: network_stats.rx_errors,
+            tx_drops: read_sysfs_counter(Path::new(SYS_CLASS_NET_PATH), network_name, "tx_dropped").unwrap_or(0),
+            rx_drops: read_sysfs_counter(Path::new(SYS_CLASS_NET_PATH), network_name, "rx_dropped").unwrap_or(0),
+            boot_id: read_boot_id(),
+            ifindex: read_ifindex(Path::new(SYS_CLASS_NET_PATH), network_name),
+        })
+    }
+}
+
+/// 直接解析 /proc/net/dev，容器中可以挂载宿主机的 /proc 后指定路径
+pub struct ProcNetDevSource {
+    path: PathBuf,
+}
+
+impl TrafficSource for ProcNetDevSource {
+    fn read(&self, network_name: &str) -> anyhow::Result<TrafficReadings> {
+        let content = file_util::read_file(&self.path)?;
+        let mut readings = parse_proc_net_dev(&content, network_name)?;
+        readings.boot_id = read_boot_id();
+        readings.ifindex = read_ifindex(Path::new(SYS_CLASS_NET_PATH), network_name);
+        anyhow::Ok(readings)
+    }
+}
+
+fn parse_proc_net_dev(content: &str, network_name: &str) -> anyhow::Result<TrafficReadings> {
+    for line in content.lines().skip(2) {
+        let (name, fields) = match line.split_once(':') {
+            Some(res) => res,
+            None => continue,
+        };
+        if name.trim() != network_name {
+            continue;
+        }
+        let fields = fields
+            .split_whitespace()
+            .map(|ele| ele.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()?;
+        if fields.len() < 16 {
+            return Err(anyhow!("/proc/net/dev 中网卡 {} 的数据列数不正确", network_name));
+        }
+        return anyhow::Ok(TrafficReadings {
+            rx_bytes: fields[0],
+            rx_packets: fields[1],
+            rx_errors: fields[2],
+            rx_drops: fields[3],
+            tx_bytes: fields[8],
+            tx_packets: fields[9],
+            tx_errors: fields[10],
+            tx_drops: fields[11],
+            ..Default::default()
+        });
+    }
+    Err(anyhow!("/proc/net/dev 中没有找到网卡 {}", network_name))
+}
+
+/// 读取 /sys/class/net/<网卡>/statistics 下的计数器文件
+pub struct SysfsSource {
+    path: PathBuf,
+}
+
+impl TrafficSource for SysfsSource {
+    fn read(&self, network_name: &str) -> anyhow::Result<TrafficReadings> {
+        anyhow::Ok(TrafficReadings {
+            tx_bytes: read_sysfs_counter(&self.path, network_name, "tx_bytes")?,
+            rx_bytes: read_sysfs_counter(&self.path, network_name, "rx_bytes")?,
+            tx_packets: read_sysfs_counter(&self.path, network_name, "tx_packets")?,
+            rx_packets: read_sysfs_counter(&self.path, network_name, "rx_packets")?,
+            tx_errors: read_sysfs_counter(&self.path, network_name, "tx_errors")?,
+            rx_errors: read_sysfs_counter(&self.path, network_name, "rx_errors")?,
+            tx_drops: read_sysfs_counter(&self.path, network_name, "tx_dropped")?,
+            rx_drops: read_sysfs_counter(&self.path, network_name, "rx_dropped")?,
+            boot_id: read_boot_id(),
+            ifindex: read_ifindex(&self.path, network_name),
+        })
+    }
+}
+
+/// 从 json 文件读取读数，格式为 {"eth0": {"tx_bytes": 0, "rx_bytes": 0, "boot_id": "", "ifindex": 2}}，每次采集都会重新读取，用于测试
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl TrafficSource for FileSource {
+    fn read(&self, network_name: &str) -> anyhow::Result<TrafficReadings> {
+        let content = file_util::read_file(&self.path)?;
+        let mut readings: HashMap<String, TrafficReadings> = serde_json::from_str(&content)?;
+        readings
+            .remove(network_name)
+            .ok_or_else(|| anyhow!("{} 中没有找到网卡 {}", self.path.display(), network_name))
+    }
+}
+
+#[cfg(test)]
+mod traffic_source_svc_test {
+    use super::*;
+
+    const PROC_NET_DEV: &str = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1296      16    0    0    0     0          0         0     1296      16    0    0    0     0       0          0
+  eth0: 987654321  654321    1    2    0     0          0         0 123456789  321654    3    4    0     0       0          0
+wg0:12345 10 0 0 0 0 0 0 54321 20 0 0 0 0 0 0
+";
+
+    #[test]
+    fn parse_proc_net_dev_test() {
+        let readings = parse_proc_net_dev(PROC_NET_DEV, "eth0").unwrap();
+        assert_eq!(readings.rx_bytes, 987654321);
+        assert_eq!(readings.tx_bytes, 123456789);
+        assert_eq!((readings.rx_packets, readings.rx_errors, readings.rx_drops), (654321, 1, 2));
+        assert_eq!((readings.tx_packets, readings.tx_errors, readings.tx_drops), (321654, 3, 4));
+
+        let readings = parse_proc_net_dev(PROC_NET_DEV, "wg0").unwrap();
+        assert_eq!(readings.rx_bytes, 12345);
+        assert_eq!(readings.tx_bytes, 54321);
+
+        assert!(parse_proc_net_dev(PROC_NET_DEV, "eth1").is_err());
+    }
+
+    #[test]
+    fn file_source_test() {
+        let path = std::env::temp_dir().join(format!("traffic-source-{}.json", std::process::id()));
+        file_util::write_file(&path, r#"{"eth0": {"tx_bytes": 100, "rx_bytes": 200}}"#);
+        let source = FileSource { path: path.clone() };
+        let readings = source.read("eth0").unwrap();
+        assert_eq!((readings.tx_bytes, readings.rx_bytes), (100, 200));
+        assert_eq!(readings.boot_id, None);
+        assert!(source.read("eth1").is_err());
+        file_util::delete_file(&path).unwrap();
+    }
+}
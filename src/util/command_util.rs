@@ -115,6 +115,63 @@ pub async fn execute_to_output(
     command.output().await
 }
 
+/// 设置环境变量执行命令，超时后结束进程，timeout 为 None 时不限制执行时间
+/// unix 下命令在单独的进程组中执行，超时后结束整个进程组，命令启动的子进程也会被结束；windows 下只结束 pwsh 进程
+pub async fn execute_to_output_with_env(
+    workdir: String,
+    commands: Vec<String>,
+    envs: &[(String, String)],
+    timeout: Option<std::time::Duration>,
+) -> Result<std::process::Output, std::io::Error> {
+    let mut command = build_command(workdir, commands);
+    command
+        .envs(envs.iter().cloned())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+    let child = command.spawn()?;
+    let pid = child.id();
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait_with_output().await,
+    };
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(res) => res,
+        Err(_) => {
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                kill_process_group(pid).await;
+            }
+            #[cfg(windows)]
+            let _ = pid;
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("执行命令超时 {} 秒", timeout.as_secs()),
+            ))
+        }
+    }
+}
+
+/// 进程组 id 与组长进程的 pid 相同
+#[cfg(unix)]
+async fn kill_process_group(pid: u32) {
+    let res = Command::new("kill")
+        .arg("-s")
+        .arg("KILL")
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .output()
+        .await;
+    match res {
+        Ok(res) if res.status.success() => {}
+        Ok(res) => tracing::warn!("结束进程组 {} 失败: {}", pid, String::from_utf8_lossy(&res.stderr)),
+        Err(e) => tracing::warn!("结束进程组 {} 失败: {:?}", pid, e),
+    }
+}
+
 #[allow(dead_code)]
 pub async fn execute_to_status(
     workdir: String,
@@ -257,4 +314,26 @@ r#"Write-Output (@"
             }
         }
     }
-}
\ No newline at end of file
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn execute_to_output_with_env_test() {
+        let envs = [("TM_TEST".to_string(), "hello".to_string())];
+        let res = execute_to_output_with_env(".".to_string(), vec!["echo $TM_TEST".to_string()], &envs, Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&res.stdout).ends_with("hello\n"));
+
+        // 超时后命令启动的子进程也被结束
+        let pid_file = std::env::temp_dir().join(format!("command_util_test_{}.pid", std::process::id()));
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let res = execute_to_output_with_env(".".to_string(), vec![command], &[], Some(Duration::from_secs(1))).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        sleep(Duration::from_millis(200)).await;
+        // 被结束的进程可能还没有被回收，状态为 Z 时同样视为已结束
+        let res = Command::new("ps").arg("-o").arg("stat=").arg("-p").arg(pid.trim()).output().await.unwrap();
+        let stat = String::from_utf8_lossy(&res.stdout).trim().to_string();
+        assert!(stat.is_empty() || stat.starts_with('Z'), "{}", stat);
+    }
+}
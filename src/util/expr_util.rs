@@ -0,0 +1,214 @@
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+
+/// 流量统计表达式，变量 in 为下行流量，out 为上行流量
+/// 支持 + - * 运算、括号、小数系数以及 sum max min 函数，例如: sum(in,out)  out + 0.1*in  max(in*0.5,out)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(Decimal),
+    In,
+    Out,
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Sum(Vec<Expr>),
+    Max(Vec<Expr>),
+    Min(Vec<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Decimal),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    LParen,
+    RParen,
+    Comma,
+}
+
+pub fn parse(text: &str) -> anyhow::Result<Expr> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(anyhow!("表达式 {} 解析失败，多余的内容: {:?}", text, token));
+    }
+    anyhow::Ok(expr)
+}
+
+/// 计算表达式，溢出时取最大值
+pub fn eval(expr: &Expr, downlink: Decimal, uplink: Decimal) -> Decimal {
+    let eval_list = |list: &Vec<Expr>| list.iter().map(|ele| eval(ele, downlink, uplink)).collect::<Vec<Decimal>>();
+    match expr {
+        Expr::Number(number) => *number,
+        Expr::In => downlink,
+        Expr::Out => uplink,
+        Expr::Neg(ele) => -eval(ele, downlink, uplink),
+        Expr::Add(left, right) => eval(left, downlink, uplink)
+            .checked_add(eval(right, downlink, uplink))
+            .unwrap_or(Decimal::MAX),
+        Expr::Sub(left, right) => eval(left, downlink, uplink)
+            .checked_sub(eval(right, downlink, uplink))
+            .unwrap_or(Decimal::MIN),
+        Expr::Mul(left, right) => eval(left, downlink, uplink)
+            .checked_mul(eval(right, downlink, uplink))
+            .unwrap_or(Decimal::MAX),
+        Expr::Sum(list) => eval_list(list)
+            .into_iter()
+            .fold(Decimal::ZERO, |sum, ele| sum.checked_add(ele).unwrap_or(Decimal::MAX)),
+        Expr::Max(list) => eval_list(list).into_iter().max().unwrap_or_default(),
+        Expr::Min(list) => eval_list(list).into_iter().min().unwrap_or_default(),
+    }
+}
+
+fn tokenize(text: &str) -> anyhow::Result<Vec<Token>> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => {}
+            '+' => tokens.push(Token::Plus),
+            '-' => tokens.push(Token::Minus),
+            '*' => tokens.push(Token::Star),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '0'..='9' | '.' => {
+                let start = i;
+                while i + 1 < chars.len() && (chars[i + 1].is_ascii_digit() || chars[i + 1] == '.') {
+                    i += 1;
+                }
+                let number: String = chars[start..=i].iter().collect();
+                let number = number
+                    .parse::<Decimal>()
+                    .map_err(|_| anyhow!("表达式 {} 解析失败，数字 {} 格式错误", text, number))?;
+                tokens.push(Token::Number(number));
+            }
+            'a'..='z' | 'A'..='Z' => {
+                let start = i;
+                while i + 1 < chars.len() && chars[i + 1].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..=i].iter().collect::<String>().to_lowercase()));
+            }
+            _ => return Err(anyhow!("表达式 {} 解析失败，不支持的字符: {}", text, c)),
+        }
+        i += 1;
+    }
+    anyhow::Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> anyhow::Result<()> {
+        match self.next() {
+            Some(token) if token == expected => anyhow::Ok(()),
+            token => Err(anyhow!("表达式解析失败，应为 {:?}，实际为 {:?}", expected, token)),
+        }
+    }
+
+    fn expr(&mut self) -> anyhow::Result<Expr> {
+        let mut left = self.term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.pos += 1;
+                    left = Expr::Add(Box::new(left), Box::new(self.term()?));
+                }
+                Some(Token::Minus) => {
+                    self.pos += 1;
+                    left = Expr::Sub(Box::new(left), Box::new(self.term()?));
+                }
+                _ => return anyhow::Ok(left),
+            }
+        }
+    }
+
+    fn term(&mut self) -> anyhow::Result<Expr> {
+        let mut left = self.factor()?;
+        while let Some(Token::Star) = self.peek() {
+            self.pos += 1;
+            left = Expr::Mul(Box::new(left), Box::new(self.factor()?));
+        }
+        anyhow::Ok(left)
+    }
+
+    fn factor(&mut self) -> anyhow::Result<Expr> {
+        match self.next() {
+            Some(Token::Number(number)) => anyhow::Ok(Expr::Number(number)),
+            Some(Token::Minus) => anyhow::Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                anyhow::Ok(expr)
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "in" => anyhow::Ok(Expr::In),
+                "out" => anyhow::Ok(Expr::Out),
+                "sum" => anyhow::Ok(Expr::Sum(self.args()?)),
+                "max" => anyhow::Ok(Expr::Max(self.args()?)),
+                "min" => anyhow::Ok(Expr::Min(self.args()?)),
+                _ => Err(anyhow!("表达式解析失败，不支持的变量或函数: {}", ident)),
+            },
+            token => Err(anyhow!("表达式解析失败，不应出现 {:?}", token)),
+        }
+    }
+
+    fn args(&mut self) -> anyhow::Result<Vec<Expr>> {
+        self.expect(Token::LParen)?;
+        let mut list = vec![self.expr()?];
+        while let Some(Token::Comma) = self.peek() {
+            self.pos += 1;
+            list.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+        anyhow::Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod expr_util_test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn calc(text: &str, downlink: Decimal, uplink: Decimal) -> Decimal {
+        eval(&parse(text).unwrap(), downlink, uplink)
+    }
+
+    #[test]
+    fn eval_test() {
+        assert_eq!(calc("sum(in,out)", dec!(100), dec!(30)), dec!(130));
+        assert_eq!(calc("max(in,out)", dec!(100), dec!(30)), dec!(100));
+        assert_eq!(calc("out", dec!(100), dec!(30)), dec!(30));
+        assert_eq!(calc("out + 0.1*in", dec!(100), dec!(30)), dec!(40));
+        assert_eq!(calc("0.5 * in + out", dec!(100), dec!(30)), dec!(80));
+        assert_eq!(calc("max(in*0.5, out) - (out - 10)", dec!(100), dec!(30)), dec!(30));
+        assert_eq!(calc("-in + MIN(in, out, 2)", dec!(1), dec!(30)), dec!(0));
+
+        assert!(parse("in / out").is_err());
+        assert!(parse("sum(in,out").is_err());
+        assert!(parse("total").is_err());
+        assert!(parse("in out").is_err());
+        assert!(parse("1.2.3").is_err());
+    }
+}
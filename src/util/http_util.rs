@@ -16,4 +16,34 @@ pub async fn post(url: &String, body: String) -> anyhow::Result<String> {
         return Err(anyhow!("Error: {:?} Error Body: {:?}", response.status(), response.text().await));
     }
     anyhow::Ok(response.text().await?)
-}
\ No newline at end of file
+}
+
+/// 按指定的方法和请求头发送请求，返回响应内容，响应状态不为 2xx 时返回错误
+pub async fn request(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<String>,
+    timeout: std::time::Duration,
+) -> anyhow::Result<String> {
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())?;
+    let mut header_map = HeaderMap::new();
+    header_map.insert(USER_AGENT_KEY, HeaderValue::from_static(USER_AGENT_VALUE));
+    header_map.insert("Content-Type", HeaderValue::from_static("application/json"));
+    for (key, value) in headers {
+        header_map.insert(reqwest::header::HeaderName::from_bytes(key.as_bytes())?, HeaderValue::from_str(value)?);
+    }
+    let client = reqwest::Client::builder()
+        .default_headers(header_map)
+        .timeout(timeout)
+        .build()?;
+    let mut request = client.request(method, url);
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Error: {:?} Error Body: {:?}", response.status(), response.text().await));
+    }
+    anyhow::Ok(response.text().await?)
+}
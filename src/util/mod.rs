@@ -2,4 +2,7 @@ pub mod file_util;
 pub mod http_util;
 pub mod command_util;
 pub mod tg_util;
-pub mod response_util;
\ No newline at end of file
+pub mod response_util;
+pub mod time_util;
+pub mod expr_util;
+pub mod traffic_util;
@@ -0,0 +1,166 @@
+use chrono::{Datelike, Duration, LocalResult, Months, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use crate::config::state::{CycleType, MonthDay};
+
+/// 当前 UTC 时间，数据库中的采集时间均为 UTC
+pub fn now() -> NaiveDateTime {
+    Utc::now().naive_utc().with_nanosecond(0).unwrap()
+}
+
+/// 配置时区的今天
+pub fn today(tz: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&tz).date_naive()
+}
+
+/// UTC 时间转为配置时区的本地时间
+pub fn to_local(time: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    Utc.from_utc_datetime(&time).with_timezone(&tz).naive_local()
+}
+
+/// 配置时区的本地时间转为 UTC 时间
+/// 夏令时回拨时本地时间出现两次，取较早的一次；夏令时跳过的本地时间不存在，顺延到跳过之后
+pub fn to_utc(time: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    match tz.from_local_datetime(&time) {
+        LocalResult::Single(res) => res.naive_utc(),
+        LocalResult::Ambiguous(earliest, _) => earliest.naive_utc(),
+        LocalResult::None => to_utc(time + Duration::hours(1), tz),
+    }
+}
+
+/// 配置时区一天开始时刻的 UTC 时间
+pub fn day_start(day: NaiveDate, tz: Tz) -> NaiveDateTime {
+    to_utc(day.and_hms_opt(0, 0, 0).unwrap(), tz)
+}
+
+/// 流量周期的今天，配置了重置小时(UTC)时以每天的重置时刻作为一天的开始
+pub fn cycle_today(reset_hour: Option<u32>, tz: Tz) -> NaiveDate {
+    cycle_day(now(), reset_hour, tz)
+}
+
+/// UTC 时间所在的流量周期日
+pub fn cycle_day(time: NaiveDateTime, reset_hour: Option<u32>, tz: Tz) -> NaiveDate {
+    match reset_hour {
+        Some(reset_hour) => (time - Duration::hours(reset_hour as i64)).date(),
+        None => to_local(time, tz).date(),
+    }
+}
+
+/// 流量周期中一天开始时刻的 UTC 时间
+pub fn cycle_day_start(day: NaiveDate, reset_hour: Option<u32>, tz: Tz) -> NaiveDateTime {
+    match reset_hour {
+        Some(reset_hour) => day.and_hms_opt(reset_hour, 0, 0).unwrap(),
+        None => day_start(day, tz),
+    }
+}
+
+/// UTC 时间所在的本地整点小时的开始时刻，返回 UTC 时间
+/// 直接按本地分秒回退，夏令时回拨时重复的本地小时会得到不同的开始时刻
+pub fn truncate_hour(time: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    let local = to_local(time, tz);
+    time - Duration::seconds((local.minute() * 60 + local.second()) as i64) - Duration::nanoseconds(local.nanosecond() as i64)
+}
+
+/// 截断到 5 分钟，各时区的偏移都是 5 分钟的整数倍，直接按 UTC 截断即可
+pub fn truncate_five_minute(time: NaiveDateTime) -> NaiveDateTime {
+    time.with_minute(time.minute() - time.minute() % 5)
+        .unwrap()
+        .with_second(0)
+        .unwrap()
+        .with_nanosecond(0)
+        .unwrap()
+}
+
+/// 上一个周期的开始日期，一次性周期没有上一个周期
+pub fn previous_cycle_start_date(cycle_type: &CycleType, current_cycle_start_date: NaiveDate) -> Option<NaiveDate> {
+    match cycle_type {
+        CycleType::ONCE(_, _) => None,
+        _ => Some(cycle_range(cycle_type, current_cycle_start_date - Duration::days(1)).0),
+    }
+}
+
+/// 包含 day 的流量周期的开始和结束日期，各次重置日期都由配置的重置日期推算，不会因为短月累积偏移
+pub fn cycle_range(cycle_type: &CycleType, day: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let (each_days, traffic_reset_date) = match cycle_type {
+        CycleType::ONCE(start, end) => return (*start, *end),
+        CycleType::DAY(each, traffic_reset_date) => (*each, *traffic_reset_date),
+        CycleType::WEEK(each, traffic_reset_date) => (each * 7, *traffic_reset_date),
+        CycleType::MONTH(each, traffic_reset_date, month_day) | CycleType::YEAR(each, traffic_reset_date, month_day) => {
+            let each_months = if let CycleType::YEAR(..) = cycle_type { each * 12 } else { *each };
+            let month_index = |date: NaiveDate| date.year() as i64 * 12 + date.month0() as i64;
+            let mut n = (month_index(day) - month_index(*traffic_reset_date)).div_euclid(each_months);
+            // 同一个月内还没到重置日时属于上一个周期
+            if month_reset_date(*traffic_reset_date, n * each_months, month_day) > day {
+                n -= 1;
+            }
+            return (
+                month_reset_date(*traffic_reset_date, n * each_months, month_day),
+                month_reset_date(*traffic_reset_date, (n + 1) * each_months, month_day) - Duration::days(1),
+            );
+        }
+    };
+    let n = (day - traffic_reset_date).num_days().div_euclid(each_days);
+    let start = traffic_reset_date + Duration::days(n * each_days);
+    (start, start + Duration::days(each_days - 1))
+}
+
+/// 重置日期所在月之后第 months 个月的重置日
+fn month_reset_date(traffic_reset_date: NaiveDate, months: i64, month_day: &MonthDay) -> NaiveDate {
+    let month_index = traffic_reset_date.year() as i64 * 12 + traffic_reset_date.month0() as i64 + months;
+    let first_day = NaiveDate::from_ymd_opt(month_index.div_euclid(12) as i32, month_index.rem_euclid(12) as u32 + 1, 1).unwrap();
+    let last_day = first_day.checked_add_months(Months::new(1)).unwrap() - Duration::days(1);
+    match month_day {
+        MonthDay::Day(day) => first_day.with_day(std::cmp::min(*day, last_day.day())).unwrap(),
+        MonthDay::Last => last_day,
+    }
+}
+
+#[cfg(test)]
+mod time_util_test {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn dst_test() {
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        // 夏令时回拨，本地 02:00 ~ 03:00 出现两次
+        assert_eq!(to_utc(time("2024-10-27T02:30:00"), tz), time("2024-10-27T00:30:00"));
+        assert_eq!(truncate_hour(time("2024-10-27T00:30:00"), tz), time("2024-10-27T00:00:00"));
+        assert_eq!(truncate_hour(time("2024-10-27T01:30:00"), tz), time("2024-10-27T01:00:00"));
+        assert_eq!(to_local(time("2024-10-27T01:30:00"), tz), time("2024-10-27T02:30:00"));
+        // 夏令时跳过，本地 02:00 ~ 03:00 不存在
+        assert_eq!(to_utc(time("2024-03-31T02:30:00"), tz), time("2024-03-31T01:30:00"));
+        assert_eq!(day_start(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), tz), time("2024-03-30T23:00:00"));
+
+        let tz: Tz = "Asia/Kolkata".parse().unwrap();
+        assert_eq!(truncate_hour(time("2024-08-05T10:10:00"), tz), time("2024-08-05T09:30:00"));
+    }
+
+    #[test]
+    fn cycle_range_test() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        // 31 日重置，短月取最后一天，之后的月份不会偏移到 28 日
+        let cycle_type = CycleType::MONTH(1, date("2024-01-31"), MonthDay::Day(31));
+        assert_eq!(cycle_range(&cycle_type, date("2024-03-15")), (date("2024-02-29"), date("2024-03-30")));
+        assert_eq!(cycle_range(&cycle_type, date("2024-03-31")), (date("2024-03-31"), date("2024-04-29")));
+        assert_eq!(cycle_range(&cycle_type, date("2023-12-01")), (date("2023-11-30"), date("2023-12-30")));
+        assert_eq!(previous_cycle_start_date(&cycle_type, date("2024-03-31")), Some(date("2024-02-29")));
+
+        let cycle_type = CycleType::MONTH(2, date("2024-08-05"), MonthDay::Last);
+        assert_eq!(cycle_range(&cycle_type, date("2024-08-05")), (date("2024-06-30"), date("2024-08-30")));
+        assert_eq!(cycle_range(&cycle_type, date("2024-09-30")), (date("2024-08-31"), date("2024-10-30")));
+
+        let cycle_type = CycleType::WEEK(1, date("2024-08-05"));
+        assert_eq!(cycle_range(&cycle_type, date("2024-08-01")), (date("2024-07-29"), date("2024-08-04")));
+        assert_eq!(cycle_range(&cycle_type, date("2024-08-05")), (date("2024-08-05"), date("2024-08-11")));
+
+        let cycle_type = CycleType::YEAR(1, date("2024-02-29"), MonthDay::Day(29));
+        assert_eq!(cycle_range(&cycle_type, date("2025-06-01")), (date("2025-02-28"), date("2026-02-27")));
+
+        let cycle_type = CycleType::DAY(10, date("2024-08-05"));
+        assert_eq!(cycle_range(&cycle_type, date("2024-08-04")), (date("2024-07-26"), date("2024-08-04")));
+    }
+}
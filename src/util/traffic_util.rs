@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+
+pub const KB: i64 = 1024;
+pub const MB: i64 = KB * 1024;
+pub const GB: i64 = MB * 1024;
+pub const TB: i64 = GB * 1024;
+
+pub fn traffic_show<T: Into<Decimal>>(bytes: T) -> String {
+    let bytes = bytes.into();
+    if bytes < Decimal::from_i64(KB).unwrap() {
+        return format!("{} B", bytes);
+    } else if bytes < Decimal::from_i64(MB).unwrap() {
+        return format!("{:.2} KB", bytes / Decimal::from_i64(KB).unwrap());
+    } else if bytes < Decimal::from_i64(GB).unwrap() {
+        return format!("{:.2} MB", bytes / Decimal::from_i64(MB).unwrap());
+    } else if bytes < Decimal::from_i64(TB).unwrap() {
+        return format!("{:.2} GB", bytes / Decimal::from_i64(GB).unwrap());
+    } else {
+        return format!("{:.2} TB", bytes / Decimal::from_i64(TB).unwrap());
+    }
+}
+
+/// 解析 1TB 500GB 这样的流量限制，没有配置时为空
+pub fn parse_traffic_limit(traffic_limit: &Option<String>, name: &str) -> anyhow::Result<Option<i64>> {
+    let traffic_limit = match traffic_limit {
+        Some(traffic_limit) => traffic_limit.replace(" ", "").replace(",", "").replace("_", ""),
+        None => return anyhow::Ok(None),
+    };
+    let traffic_limit = if let Some(traffic_limit) = traffic_limit.strip_suffix("MB") {
+        Decimal::from_str(traffic_limit)? * Decimal::from_i64(MB).unwrap()
+    } else if let Some(traffic_limit) = traffic_limit.strip_suffix("GB") {
+        Decimal::from_str(traffic_limit)? * Decimal::from_i64(GB).unwrap()
+    } else if let Some(traffic_limit) = traffic_limit.strip_suffix("TB") {
+        Decimal::from_str(traffic_limit)? * Decimal::from_i64(TB).unwrap()
+    } else {
+        return Err(anyhow!(
+            "config[liftcycle][{}] 需要以 MB GB TB 结尾",
+            name
+        ));
+    };
+    anyhow::Ok(Some(traffic_limit.trunc().to_string().parse::<i64>()?))
+}
+
+#[cfg(test)]
+mod traffic_util_test {
+    use super::*;
+
+    #[test]
+    fn parse_traffic_limit_test() {
+        assert_eq!(parse_traffic_limit(&None, "traffic_limit").unwrap(), None);
+        assert_eq!(parse_traffic_limit(&Some("1TB".to_string()), "traffic_limit").unwrap(), Some(TB));
+        assert_eq!(parse_traffic_limit(&Some("1.5 GB".to_string()), "traffic_limit").unwrap(), Some(GB + GB / 2));
+        assert_eq!(parse_traffic_limit(&Some("1_000MB".to_string()), "traffic_limit").unwrap(), Some(1000 * MB));
+        assert!(parse_traffic_limit(&Some("1PB".to_string()), "traffic_limit").is_err());
+    }
+}